@@ -0,0 +1,841 @@
+use std::collections::{HashMap, VecDeque};
+
+use thiserror::Error;
+
+use crate::models::{
+    chip8::{MEMORY_SIZE, START_ADDRESS},
+    opcode::Opcode,
+};
+
+#[derive(Error, Debug)]
+#[error("line {line}: {message}")]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+#[derive(Clone, Copy)]
+enum FixupKind {
+    /// A 12-bit address inside an instruction word.
+    Opcode(fn(u16) -> Opcode),
+    /// The 16-bit operand of `i := long`.
+    Long,
+}
+
+struct Fixup {
+    position: usize,
+    label: String,
+    line: usize,
+    kind: FixupKind,
+}
+
+enum Flow {
+    /// `if ... begin`. `jump` is the forward jump to patch at `else` or `end`.
+    If { jump: usize },
+    /// `loop`. `breaks` are the forward jumps emitted by `while`.
+    Loop { start: u16, breaks: Vec<usize> },
+}
+
+/// A skip pair for a condition: the skip taken when it holds, and when it does not.
+struct Condition {
+    skip_if_true: Opcode,
+    skip_if_false: Opcode,
+}
+
+/// Assembles Octo source into a ROM image that starts at 0x200.
+///
+/// Supported: labels, `:const`, `:alias`, `:macro` (expanded by naming it or
+/// with `:calls NAME`), `:org`, `:byte`, `:call`, `if ... then`,
+/// `if ... begin ... else ... end`, `loop ... while ... again`,
+/// and the CHIP-8, SUPER-CHIP and XO-CHIP statements. If the program defines
+/// `main` anywhere but at the very start, a `jump main` is emitted at 0x200.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler::new(tokenize(source));
+    assembler.run()?;
+    assembler.finish()
+}
+
+fn tokenize(source: &str) -> VecDeque<Token> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace()
+                .map(|text| Token {
+                    text: text.to_string(),
+                    line: index + 1,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    rom: Vec<u8>,
+    position: usize,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, u16>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    flow: Vec<Flow>,
+    line: usize,
+}
+
+impl Assembler {
+    fn new(tokens: VecDeque<Token>) -> Self {
+        Self {
+            tokens,
+            rom: Vec::new(),
+            position: START_ADDRESS,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            flow: Vec::new(),
+            line: 1,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), AsmError> {
+        let starts_with_main = self.tokens.front().is_some_and(|token| token.text == ":")
+            && self.tokens.get(1).is_some_and(|token| token.text == "main");
+        let defines_main = self
+            .tokens
+            .iter()
+            .zip(self.tokens.iter().skip(1))
+            .any(|(colon, name)| colon.text == ":" && name.text == "main");
+        if defines_main && !starts_with_main {
+            self.emit_address("main", FixupKind::Opcode(Opcode::Jump))?;
+        }
+
+        while let Some(token) = self.tokens.pop_front() {
+            self.line = token.line;
+            self.statement(&token.text)?;
+        }
+
+        if !self.flow.is_empty() {
+            return Err(self.error("Unterminated if/begin or loop block"));
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        for fixup in std::mem::take(&mut self.fixups) {
+            self.line = fixup.line;
+            let addr = *self
+                .labels
+                .get(&fixup.label)
+                .ok_or_else(|| self.error(format!("Undefined label {}", fixup.label)))?;
+            self.position = fixup.position;
+            self.write_address(addr, fixup.kind)?;
+        }
+
+        Ok(self.rom)
+    }
+
+    fn next(&mut self) -> Result<String, AsmError> {
+        let token = self
+            .tokens
+            .pop_front()
+            .ok_or_else(|| self.error("Unexpected end of input"))?;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.front().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(self.error(format!("Expected {expected}, found {token}")));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: &str) -> Result<(), AsmError> {
+        match token {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(name)
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.next()?;
+                let value = self.number(&value)?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let register = self.next()?;
+                let register = self.register(&register)?;
+                self.aliases.insert(name, register);
+                Ok(())
+            }
+            ":macro" => self.define_macro(),
+            ":calls" => {
+                let name = self.next()?;
+                if !self.macros.contains_key(&name) {
+                    return Err(self.error(format!("Unknown macro {name}")));
+                }
+                self.expand_macro(&name)
+            }
+            ":org" => {
+                let addr = self.next()?;
+                let addr = self.number(&addr)? as usize;
+                if !(START_ADDRESS..MEMORY_SIZE).contains(&addr) {
+                    return Err(self.error(format!(":org {addr:#x} is outside the ROM")));
+                }
+                self.position = addr;
+                Ok(())
+            }
+            ":byte" => {
+                let value = self.next()?;
+                let value = self.byte(&value)?;
+                self.emit_byte(value)
+            }
+            ":call" => {
+                let target = self.next()?;
+                self.emit_address(&target, FixupKind::Opcode(Opcode::CallSubroutine))
+            }
+            "clear" => self.emit(Opcode::ClearScreen),
+            "return" | ";" => self.emit(Opcode::ReturnFromSubroutine),
+            "exit" => self.emit(Opcode::Exit),
+            "lores" => self.emit(Opcode::LowResolution),
+            "hires" => self.emit(Opcode::HighResolution),
+            "scroll-down" => {
+                let n = self.nibble_operand()?;
+                self.emit(Opcode::ScrollDown(n))
+            }
+            "scroll-up" => {
+                let n = self.nibble_operand()?;
+                self.emit(Opcode::ScrollUp(n))
+            }
+            "scroll-left" => self.emit(Opcode::ScrollLeft),
+            "scroll-right" => self.emit(Opcode::ScrollRight),
+            "audio" => self.emit(Opcode::LoadAudioPattern),
+            "plane" => {
+                let n = self.nibble_operand()?;
+                self.emit(Opcode::SelectPlanes(n))
+            }
+            "bcd" => {
+                let x = self.register_operand()?;
+                self.emit(Opcode::BinaryCodedDecimal(x))
+            }
+            "saveflags" => {
+                let x = self.register_operand()?;
+                self.emit(Opcode::SaveFlags(x))
+            }
+            "loadflags" => {
+                let x = self.register_operand()?;
+                self.emit(Opcode::LoadFlags(x))
+            }
+            "save" | "load" => {
+                let x = self.register_operand()?;
+                let range_end = if self.peek() == Some("-") {
+                    self.next()?;
+                    Some(self.register_operand()?)
+                } else {
+                    None
+                };
+
+                self.emit(match (token, range_end) {
+                    ("save", None) => Opcode::Dump(x),
+                    ("load", None) => Opcode::Load(x),
+                    ("save", Some(y)) => Opcode::SaveRange(x, y),
+                    (_, Some(y)) => Opcode::LoadRange(x, y),
+                    _ => Opcode::Load(x),
+                })
+            }
+            "sprite" => {
+                let x = self.register_operand()?;
+                let y = self.register_operand()?;
+                let n = self.nibble_operand()?;
+                self.emit(Opcode::Draw(x, y, n))
+            }
+            "jump" => {
+                let target = self.next()?;
+                self.emit_address(&target, FixupKind::Opcode(Opcode::Jump))
+            }
+            "jump0" => {
+                let target = self.next()?;
+                self.emit_address(&target, FixupKind::Opcode(Opcode::JumpPlus))
+            }
+            "i" => self.index_statement(),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register_operand()?;
+                self.emit(match token {
+                    "delay" => Opcode::SetDelayTimer(x),
+                    "buzzer" => Opcode::SetSoundTimer(x),
+                    _ => Opcode::SetPitch(x),
+                })
+            }
+            "if" => self.if_statement(),
+            "else" => self.else_statement(),
+            "end" => self.end_statement(),
+            "loop" => {
+                self.flow.push(Flow::Loop {
+                    start: self.position as u16,
+                    breaks: Vec::new(),
+                });
+                Ok(())
+            }
+            "while" => self.while_statement(),
+            "again" => self.again_statement(),
+            _ if self.is_register(token) => {
+                let x = self.register(token)?;
+                self.register_statement(x)
+            }
+            _ if self.macros.contains_key(token) => self.expand_macro(token),
+            _ => match self.value(token)? {
+                // Bare numbers are emitted as data bytes.
+                Some(_) if !self.labels.contains_key(token) => {
+                    let value = self.byte(token)?;
+                    self.emit_byte(value)
+                }
+                // Bare label names are subroutine calls.
+                _ if is_identifier(token) => {
+                    self.emit_address(token, FixupKind::Opcode(Opcode::CallSubroutine))
+                }
+                _ => Err(self.error(format!("Unknown statement {token}"))),
+            },
+        }
+    }
+
+    fn define_label(&mut self, name: String) -> Result<(), AsmError> {
+        if self.labels.contains_key(&name) {
+            return Err(self.error(format!("Label {name} defined twice")));
+        }
+        self.labels.insert(name, self.position as u16);
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.next()?;
+        let mut params = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token == "{" {
+                break;
+            }
+            params.push(token);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self
+                .tokens
+                .pop_front()
+                .ok_or_else(|| self.error(format!("Unterminated macro {name}")))?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), AsmError> {
+        let params = self.macros[name].params.clone();
+        let mut args = HashMap::new();
+        for param in params {
+            let arg = self.next()?;
+            args.insert(param, arg);
+        }
+
+        let line = self.line;
+        let body = &self.macros[name].body;
+        for token in body.iter().rev() {
+            let text = args.get(&token.text).unwrap_or(&token.text).clone();
+            self.tokens.push_front(Token { text, line });
+        }
+
+        Ok(())
+    }
+
+    fn index_statement(&mut self) -> Result<(), AsmError> {
+        let op = self.next()?;
+        match op.as_str() {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.next()?;
+                    let x = self.register_operand()?;
+                    self.emit(Opcode::SpriteAddress(x))
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    let x = self.register_operand()?;
+                    self.emit(Opcode::BigSpriteAddress(x))
+                }
+                Some("long") => {
+                    self.next()?;
+                    let target = self.next()?;
+                    self.emit(Opcode::SetILong)?;
+                    self.emit_address(&target, FixupKind::Long)
+                }
+                _ => {
+                    let target = self.next()?;
+                    self.emit_address(&target, FixupKind::Opcode(Opcode::SetI))
+                }
+            },
+            "+=" => {
+                let x = self.register_operand()?;
+                self.emit(Opcode::AddMemory(x))
+            }
+            _ => Err(self.error(format!("Unknown operator i {op}"))),
+        }
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+        let op = self.next()?;
+        let rhs = self.next()?;
+
+        if op == ":=" {
+            let opcode = match rhs.as_str() {
+                "random" => {
+                    let mask = self.next()?;
+                    Opcode::RandAnd(x, self.byte(&mask)?)
+                }
+                "delay" => Opcode::GetDelayTimer(x),
+                "key" => Opcode::GetKey(x),
+                _ if self.is_register(&rhs) => Opcode::SetV(x, self.register(&rhs)?),
+                _ => Opcode::SetVConstant(x, self.byte(&rhs)?),
+            };
+            return self.emit(opcode);
+        }
+
+        if !self.is_register(&rhs) {
+            let n = self.byte(&rhs)?;
+            return match op.as_str() {
+                "+=" => self.emit(Opcode::AddConstant(x, n)),
+                "-=" => self.emit(Opcode::AddConstant(x, n.wrapping_neg())),
+                _ => Err(self.error(format!("Operator {op} needs a register operand"))),
+            };
+        }
+
+        let y = self.register(&rhs)?;
+        let opcode = match op.as_str() {
+            "+=" => Opcode::Add(x, y),
+            "-=" => Opcode::Subtract(x, y),
+            "=-" => Opcode::SubtractOpposite(x, y),
+            "|=" => Opcode::Or(x, y),
+            "&=" => Opcode::And(x, y),
+            "^=" => Opcode::Xor(x, y),
+            ">>=" => Opcode::ShiftRight(x, y),
+            "<<=" => Opcode::ShiftLeft(x, y),
+            _ => return Err(self.error(format!("Unknown operator {op}"))),
+        };
+        self.emit(opcode)
+    }
+
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.register_operand()?;
+        let op = self.next()?;
+        let (skip_if_true, skip_if_false) = match op.as_str() {
+            "key" => (Opcode::SkipKeyEqual(x), Opcode::SkipKeyNonEqual(x)),
+            "-key" => (Opcode::SkipKeyNonEqual(x), Opcode::SkipKeyEqual(x)),
+            "==" | "!=" => {
+                let rhs = self.next()?;
+                let (equal, non_equal) = if self.is_register(&rhs) {
+                    let y = self.register(&rhs)?;
+                    (
+                        Opcode::SkipRegistersEqual(x, y),
+                        Opcode::SkipRegistersNonEqual(x, y),
+                    )
+                } else {
+                    let n = self.byte(&rhs)?;
+                    (Opcode::SkipEqual(x, n), Opcode::SkipNonEqual(x, n))
+                };
+
+                if op == "==" {
+                    (equal, non_equal)
+                } else {
+                    (non_equal, equal)
+                }
+            }
+            _ => return Err(self.error(format!("Unsupported comparison {op}"))),
+        };
+
+        Ok(Condition {
+            skip_if_true,
+            skip_if_false,
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<(), AsmError> {
+        let condition = self.condition()?;
+        match self.next()?.as_str() {
+            // The next statement runs only if the condition holds.
+            "then" => self.emit(condition.skip_if_false),
+            "begin" => {
+                self.emit(condition.skip_if_true)?;
+                let jump = self.position;
+                self.emit(Opcode::Jump(0))?;
+                self.flow.push(Flow::If { jump });
+                Ok(())
+            }
+            other => Err(self.error(format!("Expected then or begin, found {other}"))),
+        }
+    }
+
+    fn else_statement(&mut self) -> Result<(), AsmError> {
+        let Some(Flow::If { jump }) = self.flow.pop() else {
+            return Err(self.error("else without if ... begin"));
+        };
+
+        let skip_else = self.position;
+        self.emit(Opcode::Jump(0))?;
+        self.patch_jump(jump, self.position)?;
+        self.flow.push(Flow::If { jump: skip_else });
+        Ok(())
+    }
+
+    fn end_statement(&mut self) -> Result<(), AsmError> {
+        let Some(Flow::If { jump }) = self.flow.pop() else {
+            return Err(self.error("end without if ... begin"));
+        };
+        self.patch_jump(jump, self.position)
+    }
+
+    fn while_statement(&mut self) -> Result<(), AsmError> {
+        let condition = self.condition()?;
+        self.emit(condition.skip_if_true)?;
+        let jump = self.position;
+        self.emit(Opcode::Jump(0))?;
+
+        match self.flow.iter_mut().rev().find_map(|flow| match flow {
+            Flow::Loop { breaks, .. } => Some(breaks),
+            Flow::If { .. } => None,
+        }) {
+            Some(breaks) => {
+                breaks.push(jump);
+                Ok(())
+            }
+            None => Err(self.error("while outside of loop")),
+        }
+    }
+
+    fn again_statement(&mut self) -> Result<(), AsmError> {
+        let Some(Flow::Loop { start, breaks }) = self.flow.pop() else {
+            return Err(self.error("again without loop"));
+        };
+
+        self.write_address(start, FixupKind::Opcode(Opcode::Jump))?;
+        for jump in breaks {
+            self.patch_jump(jump, self.position)?;
+        }
+        Ok(())
+    }
+
+    fn patch_jump(&mut self, position: usize, target: usize) -> Result<(), AsmError> {
+        let end = self.position;
+        self.position = position;
+        self.write_address(target as u16, FixupKind::Opcode(Opcode::Jump))?;
+        self.position = end;
+        Ok(())
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.aliases.contains_key(token) || parse_register(token).is_some()
+    }
+
+    fn register(&self, token: &str) -> Result<u8, AsmError> {
+        self.aliases
+            .get(token)
+            .copied()
+            .or_else(|| parse_register(token))
+            .ok_or_else(|| self.error(format!("Expected a register, found {token}")))
+    }
+
+    fn register_operand(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        self.register(&token)
+    }
+
+    fn nibble_operand(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        let value = self.number(&token)?;
+        if value > 0xF {
+            return Err(self.error(format!("{token} does not fit in 4 bits")));
+        }
+        Ok(value as u8)
+    }
+
+    /// Resolves a literal, constant or already defined label.
+    fn value(&self, token: &str) -> Result<Option<u16>, AsmError> {
+        if let Some(value) = self.constants.get(token) {
+            return Ok(Some(*value));
+        }
+        if let Some(addr) = self.labels.get(token) {
+            return Ok(Some(*addr));
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            return parse_number(token)
+                .map(Some)
+                .ok_or_else(|| self.error(format!("Invalid number {token}")));
+        }
+        Ok(None)
+    }
+
+    fn number(&self, token: &str) -> Result<u16, AsmError> {
+        self.value(token)?
+            .ok_or_else(|| self.error(format!("Unknown value {token}")))
+    }
+
+    fn byte(&self, token: &str) -> Result<u8, AsmError> {
+        let value = self.number(token)?;
+        // Negative literals arrive as 16-bit two's complement.
+        if value > 0xFF && value < 0xFF80 {
+            return Err(self.error(format!("{token} does not fit in a byte")));
+        }
+        Ok(value as u8)
+    }
+
+    fn emit(&mut self, opcode: Opcode) -> Result<(), AsmError> {
+        let word = opcode.encode();
+        self.emit_byte((word >> 8) as u8)?;
+        self.emit_byte(word as u8)
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), AsmError> {
+        if self.position >= MEMORY_SIZE {
+            return Err(self.error("Program does not fit in memory"));
+        }
+
+        let offset = self.position - START_ADDRESS;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Emits an instruction whose address may be a label defined later.
+    fn emit_address(&mut self, target: &str, kind: FixupKind) -> Result<(), AsmError> {
+        match self.value(target)? {
+            Some(addr) => self.write_address(addr, kind),
+            None if is_identifier(target) => {
+                self.fixups.push(Fixup {
+                    position: self.position,
+                    label: target.to_string(),
+                    line: self.line,
+                    kind,
+                });
+                self.write_address(0, kind)
+            }
+            None => Err(self.error(format!("Invalid address {target}"))),
+        }
+    }
+
+    fn write_address(&mut self, addr: u16, kind: FixupKind) -> Result<(), AsmError> {
+        match kind {
+            FixupKind::Opcode(opcode) => {
+                if addr > 0xFFF {
+                    return Err(
+                        self.error(format!("Address {addr:#x} needs 16 bits, use i := long"))
+                    );
+                }
+                self.emit(opcode(addr))
+            }
+            FixupKind::Long => {
+                self.emit_byte((addr >> 8) as u8)?;
+                self.emit_byte(addr as u8)
+            }
+        }
+    }
+}
+
+fn parse_register(token: &str) -> Option<u8> {
+    let index = token.strip_prefix(['v', 'V'])?;
+    if index.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(index, 16).ok()
+}
+
+/// Parses decimal, `0x` hexadecimal and `0b` binary literals, optionally negative.
+fn parse_number(token: &str) -> Option<u16> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        u32::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<u32>().ok()?
+    };
+
+    if value > 0xFFFF {
+        return None;
+    }
+
+    let value = value as u16;
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+fn is_identifier(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble_octo;
+
+    fn error(source: &str) -> String {
+        assemble(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn if_then_skips_one_statement() {
+        assert_eq!(
+            assemble("v0 := 1 if v0 == 1 then v1 := 2").unwrap(),
+            [0x60, 0x01, 0x40, 0x01, 0x61, 0x02]
+        );
+    }
+
+    #[test]
+    fn if_else_jumps_over_the_other_branch() {
+        let rom = assemble("if v0 == 1 begin v1 := 1 else v1 := 2 end").unwrap();
+        assert_eq!(
+            rom,
+            [0x30, 0x01, 0x12, 0x08, 0x61, 0x01, 0x12, 0x0A, 0x61, 0x02]
+        );
+    }
+
+    #[test]
+    fn while_breaks_out_of_the_loop() {
+        let rom = assemble("loop v0 += 1 while v0 != 5 again").unwrap();
+        assert_eq!(rom, [0x70, 0x01, 0x40, 0x05, 0x12, 0x08, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn macros_expand_by_name_and_with_calls() {
+        let rom = assemble(":macro inc reg { reg += 1 } inc v3 :calls inc v4").unwrap();
+        assert_eq!(rom, [0x73, 0x01, 0x74, 0x01]);
+    }
+
+    #[test]
+    fn org_aliases_and_constants() {
+        let rom = assemble(":alias x v5 :const N 0x10 :org 0x204 x := N").unwrap();
+        assert_eq!(rom, [0, 0, 0, 0, 0x65, 0x10]);
+    }
+
+    #[test]
+    fn numbers_in_every_base() {
+        let rom = assemble("v0 := 0b101 v1 := 0x1F v2 := 17 v3 := -1").unwrap();
+        assert_eq!(rom, [0x60, 0x05, 0x61, 0x1F, 0x62, 0x11, 0x63, 0xFF]);
+    }
+
+    #[test]
+    fn main_after_subroutines_gets_an_entry_jump() {
+        let rom = assemble(": helper return : main helper").unwrap();
+        assert_eq!(rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+    }
+
+    #[test]
+    fn reports_errors_with_their_line() {
+        assert_eq!(
+            error("clear\njump nowhere"),
+            "line 2: Undefined label nowhere"
+        );
+        assert_eq!(error("v0 := 0x100"), "line 1: 0x100 does not fit in a byte");
+        assert_eq!(error("clear\nelse"), "line 2: else without if ... begin");
+        assert_eq!(
+            error("loop\nclear"),
+            "line 2: Unterminated if/begin or loop block"
+        );
+        assert_eq!(error(":calls nothing"), "line 1: Unknown macro nothing");
+        assert_eq!(
+            error(":org 0x1000\nloop\nagain"),
+            "line 3: Address 0x1000 needs 16 bits, use i := long"
+        );
+    }
+
+    #[test]
+    fn disassembly_assembles_back_to_the_same_bytes() {
+        let source = "
+            :const SPEED 3
+            : main
+                clear
+                i := ball
+                v0 := 0
+                loop
+                    sprite v0 v0 2
+                    v0 += SPEED
+                    if v0 == 30 begin
+                        draw-twice
+                    else
+                        v1 := random 0xFF
+                    end
+                    while v0 != 60
+                again
+                i := long ball
+                exit
+            : draw-twice
+                sprite v0 v0 2
+                sprite v0 v0 2
+                return
+            : ball
+                0x60 0x60
+        ";
+        let rom = assemble(source).unwrap();
+        assert_eq!(assemble(&disassemble_octo(&rom)).unwrap(), rom);
+    }
+}
@@ -0,0 +1,20 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chip_8::asm::assemble;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let filename = args.get(1).context("Usage: chip8-asm SOURCE [OUTPUT]")?;
+    let source =
+        std::fs::read_to_string(filename).with_context(|| format!("Cannot read {filename}"))?;
+    let rom = assemble(&source).with_context(|| format!("Cannot assemble {filename}"))?;
+
+    let output = match args.get(2) {
+        Some(output) => output.into(),
+        None => Path::new(filename).with_extension("ch8"),
+    };
+    std::fs::write(&output, rom).with_context(|| format!("Cannot write {}", output.display()))?;
+
+    Ok(())
+}
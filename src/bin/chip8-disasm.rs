@@ -0,0 +1,29 @@
+use anyhow::{Context, Result};
+use chip_8::disasm::{disassemble, disassemble_octo};
+
+fn main() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let octo = args.first().is_some_and(|arg| arg == "--octo");
+    if octo {
+        args.remove(0);
+    }
+
+    let filename = args
+        .first()
+        .context("Usage: chip8-disasm [--octo] ROM [OUTPUT]")?;
+    let rom = std::fs::read(filename).with_context(|| format!("Cannot read {filename}"))?;
+    let listing = if octo {
+        disassemble_octo(&rom)
+    } else {
+        disassemble(&rom)
+    };
+
+    match args.get(1) {
+        Some(output) => {
+            std::fs::write(output, listing).with_context(|| format!("Cannot write {output}"))?
+        }
+        None => print!("{listing}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::{fs::File, io::BufWriter};
+
+use anyhow::{bail, Context, Result};
+use chip_8::{
+    frontend::{
+        null::Null,
+        options::{rom_path, CommonOptions},
+        script::{KeyPress, ScriptedInput},
+        Emulator, FrameOutcome,
+    },
+    hash::framebuffer_hash,
+    image::{encode_pbm, encode_png},
+    models::{audio::DEFAULT_BEEP_FREQUENCY, chip8::Chip8},
+    palette::Palette,
+    video::{GifEncoder, Y4mEncoder},
+    wav::{WavWriter, WAV_SAMPLE_RATE},
+};
+
+/// Headless runs are reproducible unless a seed is given explicitly.
+const DEFAULT_SEED: u64 = 0;
+
+const USAGE: &str = "Usage: chip8-run --headless --frames N [--ipf N | --hz N] [--quirks PRESET] \
+                     [--quirk NAME=VALUE]... [--seed N] [--press KEY@FRAME[+FRAMES]]... \
+                     [--screenshot FILE.png|FILE.pbm] [--gif FILE] [--y4m FILE] [--scale N] \
+                     [--wav FILE] [--palette NAME|COLOURS] [--expect-hash HEX] ROM";
+
+struct Options {
+    filename: String,
+    frames: u32,
+    common: CommonOptions,
+    seed: u64,
+    presses: Vec<KeyPress>,
+    screenshot: Option<String>,
+    gif: Option<String>,
+    y4m: Option<String>,
+    scale: usize,
+    wav: Option<String>,
+    palette: Palette,
+    expected_hash: Option<u64>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut filename = None;
+        let mut frames = None;
+        let mut common = CommonOptions::default();
+        let mut presses = Vec::new();
+        let mut screenshot = None;
+        let mut gif = None;
+        let mut y4m = None;
+        let mut scale = 1;
+        let mut wav = None;
+        let mut expected_hash = None;
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                // There is no windowed mode; the flag documents intent in scripts.
+                "--headless" => {}
+                "--frames" => {
+                    let value = args.next().context("--frames expects a frame count")?;
+                    frames = Some(value.parse().context("Invalid --frames value")?);
+                }
+                "--press" => {
+                    let value = args.next().context("--press expects KEY@FRAME[+FRAMES]")?;
+                    presses.push(value.parse()?);
+                }
+                "--screenshot" => {
+                    let value = args.next().context("--screenshot expects a file name")?;
+                    screenshot = Some(value.clone());
+                }
+                "--gif" => {
+                    gif = Some(args.next().context("--gif expects a file name")?.clone());
+                }
+                "--y4m" => {
+                    y4m = Some(args.next().context("--y4m expects a file name")?.clone());
+                }
+                "--scale" => {
+                    let value = args.next().context("--scale expects a pixel size")?;
+                    scale = value.parse().context("Invalid --scale value")?;
+                }
+                "--wav" => {
+                    wav = Some(args.next().context("--wav expects a file name")?.clone());
+                }
+                "--expect-hash" => {
+                    let value = args
+                        .next()
+                        .context("--expect-hash expects a hexadecimal hash")?;
+                    let hash = u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                        .context("Invalid --expect-hash value")?;
+                    expected_hash = Some(hash);
+                }
+                // Nothing is shown and nothing can be rewound.
+                "--persistence" | "--rewind-mb" => bail!("{arg} has no effect in headless runs"),
+                _ if common.parse_flag(arg, &mut args)? => {}
+                _ => filename = Some(rom_path(arg, USAGE)),
+            }
+        }
+
+        let common = common.finish()?;
+        Ok(Self {
+            filename: filename.context(USAGE)?,
+            frames: frames.context(USAGE)?,
+            seed: common.seed.unwrap_or(DEFAULT_SEED),
+            palette: common.palette.clone().unwrap_or_default(),
+            common,
+            presses,
+            screenshot,
+            gif,
+            y4m,
+            scale,
+            wav,
+            expected_hash,
+        })
+    }
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let options = Options::parse(&args)?;
+    let rom = std::fs::read(&options.filename)
+        .with_context(|| format!("Cannot read {}", options.filename))?;
+    let chip = Chip8::new(rom, options.common.quirks)?.with_seed(options.seed);
+
+    let input = ScriptedInput::new(options.presses);
+    let mut emulator = Emulator::new(chip, Null, Null, input, options.common.cycles_per_frame)
+        .with_palette(options.palette.clone());
+    if let Some(path) = &options.gif {
+        let file = File::create(path).with_context(|| format!("Cannot create {path}"))?;
+        let encoder = GifEncoder::new(BufWriter::new(file), options.scale);
+        emulator = emulator.with_video(path, Box::new(encoder));
+    }
+    if let Some(path) = &options.y4m {
+        let file = File::create(path).with_context(|| format!("Cannot create {path}"))?;
+        let encoder = Y4mEncoder::new(BufWriter::new(file), options.scale);
+        emulator = emulator.with_video(path, Box::new(encoder));
+    }
+    if let Some(path) = &options.wav {
+        let file = File::create(path).with_context(|| format!("Cannot create {path}"))?;
+        let writer = WavWriter::new(
+            BufWriter::new(file),
+            WAV_SAMPLE_RATE,
+            DEFAULT_BEEP_FREQUENCY,
+        )
+        .with_context(|| format!("Cannot write {path}"))?;
+        emulator = emulator.with_wav(path, writer);
+    }
+
+    for _ in 0..options.frames {
+        match emulator.step_frame()? {
+            FrameOutcome::Running => {}
+            FrameOutcome::Halted | FrameOutcome::Quit => break,
+            FrameOutcome::Crashed => {
+                emulator.finish_recordings()?;
+                bail!("{} crashed", options.filename)
+            }
+        }
+    }
+    emulator.finish_recordings()?;
+
+    let gfx = emulator.chip.framebuffer();
+    if let Some(path) = &options.screenshot {
+        let image = if path.to_ascii_lowercase().ends_with(".pbm") {
+            encode_pbm(gfx, options.scale)
+        } else {
+            encode_png(gfx, options.scale, &options.palette)
+        };
+        std::fs::write(path, image).with_context(|| format!("Cannot write {path}"))?;
+    }
+
+    // The hash is the only thing on stdout, so scripts can capture it;
+    // everything else goes to stderr.
+    let hash = framebuffer_hash(gfx);
+    println!("{hash:016x}");
+
+    if let Some(expected) = options.expected_hash {
+        if hash != expected {
+            bail!("Framebuffer hash {hash:016x} does not match the expected {expected:016x}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use chip_8::{
+    frontend::{
+        bindings::Config,
+        null::Null,
+        options::{rom_path, CommonOptions},
+        terminal::{self, Glyphs, DEFAULT_KEY_HOLD},
+        Emulator,
+    },
+    hash::fnv1a,
+    models::chip8::Chip8,
+    rewind::Rewind,
+};
+
+const USAGE: &str = "Usage: chip8-term [--glyphs half|braille] [--ipf N | --hz N] \
+                     [--quirks PRESET] [--quirk NAME=VALUE]... [--seed N] [--rewind-mb MB] \
+                     [--key-hold MS] [--config FILE] [--palette NAME|COLOURS] \
+                     [--persistence MODE] ROM";
+
+struct Options {
+    filename: String,
+    glyphs: Glyphs,
+    common: CommonOptions,
+    key_hold: Duration,
+    config: Option<PathBuf>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut filename = None;
+        let mut glyphs = Glyphs::default();
+        let mut common = CommonOptions::default();
+        let mut key_hold = DEFAULT_KEY_HOLD;
+        let mut config = None;
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--glyphs" => {
+                    let value = args.next().context("--glyphs expects half or braille")?;
+                    glyphs = value.parse()?;
+                }
+                "--key-hold" => {
+                    let value = args
+                        .next()
+                        .context("--key-hold expects a time in milliseconds")?;
+                    let millis = value.parse().context("Invalid --key-hold value")?;
+                    key_hold = Duration::from_millis(millis);
+                }
+                "--config" => {
+                    let value = args.next().context("--config expects a TOML file")?;
+                    config = Some(PathBuf::from(value));
+                }
+                _ if common.parse_flag(arg, &mut args)? => {}
+                _ => filename = Some(rom_path(arg, USAGE)),
+            }
+        }
+
+        Ok(Self {
+            filename: filename.context(USAGE)?,
+            glyphs,
+            common: common.finish()?,
+            key_hold,
+            config,
+        })
+    }
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let options = Options::parse(&args)?;
+    let rom = std::fs::read(&options.filename)
+        .with_context(|| format!("Cannot read {}", options.filename))?;
+    let config = Config::load(options.config.as_deref(), fnv1a(&rom))?;
+
+    let mut chip = Chip8::new(rom, options.common.quirks)?;
+    if let Some(seed) = options.common.seed {
+        chip = chip.with_seed(seed);
+    }
+
+    let (display, input) = terminal::init(options.glyphs, &config.bindings, options.key_hold)?;
+    let palette = options
+        .common
+        .palette
+        .or(config.palette)
+        .unwrap_or_default();
+    let mut emulator = Emulator::new(chip, display, Null, input, options.common.cycles_per_frame)
+        .with_save_slots(&options.filename)
+        .with_palette(palette)
+        .with_persistence(options.common.persistence);
+    if options.common.rewind_mb > 0 {
+        emulator = emulator.with_rewind(Rewind::new(options.common.rewind_mb << 20));
+    }
+
+    emulator.run()
+}
@@ -0,0 +1,55 @@
+/// Little-endian cursor over a binary file, reporting problems through
+/// the file format's own error variant.
+pub(crate) struct Reader<'a, E> {
+    data: &'a [u8],
+    error: fn(String) -> E,
+}
+
+impl<'a, E> Reader<'a, E> {
+    pub fn new(data: &'a [u8], error: fn(String) -> E) -> Self {
+        Self { data, error }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], E> {
+        if self.data.len() < len {
+            return Err((self.error)("truncated".to_string()));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], E> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, E> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, E> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, E> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, E> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, E> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err((self.error)("bad flag".to_string())),
+        }
+    }
+}
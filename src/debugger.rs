@@ -0,0 +1,543 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    io::{BufRead, Write},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use crate::{
+    input::keyboard::Keyboard,
+    models::{
+        chip8::{AccessKind, Chip8},
+        errors::ChipErrors,
+    },
+};
+
+const HELP: &str = "\
+Commands:
+  s, step [N]            execute N instructions (default 1)
+  c, continue            run until the next breakpoint or watchpoint
+  p, pause               stop execution
+  b, break ADDR          set a PC breakpoint
+  d, delete ADDR         remove a PC breakpoint
+  w, watch read ADDR     stop after an instruction reads ADDR
+  w, watch write ADDR    stop after an instruction writes ADDR
+  w, watch reg REG       stop after REG (v0-vf, i, pc, sp, dt, st) changes
+  u, unwatch ...         remove a watchpoint, same syntax as watch
+  l, list                show breakpoints and watchpoints
+  r, regs                show registers, stack and timers
+  m, mem ADDR [LEN]      dump LEN bytes of memory (default 16)
+  q, quit                close the emulator
+  h, help                show this message";
+
+/// A register that can be watched for changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Register {
+    fn read(&self, chip: &Chip8) -> u16 {
+        match self {
+            Register::V(x) => chip.registers()[*x as usize] as u16,
+            Register::I => chip.index(),
+            Register::Pc => chip.pc(),
+            Register::Sp => chip.sp(),
+            Register::DelayTimer => chip.delay_timer() as u16,
+            Register::SoundTimer => chip.sound_timer() as u16,
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "i" => Ok(Register::I),
+            "pc" => Ok(Register::Pc),
+            "sp" => Ok(Register::Sp),
+            "dt" => Ok(Register::DelayTimer),
+            "st" => Ok(Register::SoundTimer),
+            _ => name
+                .strip_prefix('v')
+                .filter(|index| index.len() == 1)
+                .and_then(|index| u8::from_str_radix(index, 16).ok())
+                .map(Register::V)
+                .ok_or_else(|| format!("Unknown register {name}")),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{x:X}"),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+            Register::DelayTimer => write!(f, "DT"),
+            Register::SoundTimer => write!(f, "ST"),
+        }
+    }
+}
+
+/// Why the debugger paused execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Step,
+    Breakpoint(u16),
+    MemoryRead(usize),
+    MemoryWrite(usize),
+    RegisterChanged {
+        register: Register,
+        old: u16,
+        new: u16,
+    },
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Step => write!(f, "step"),
+            StopReason::Breakpoint(addr) => write!(f, "breakpoint at {addr:04x}"),
+            StopReason::MemoryRead(addr) => write!(f, "read of {addr:04x}"),
+            StopReason::MemoryWrite(addr) => write!(f, "write to {addr:04x}"),
+            StopReason::RegisterChanged { register, old, new } => {
+                write!(f, "{register} changed from {old:02x} to {new:02x}")
+            }
+        }
+    }
+}
+
+/// Outcome of running one frame under the debugger.
+#[derive(Debug, Default)]
+pub struct DebugFrame {
+    pub draw_update: bool,
+    pub stopped: Option<StopReason>,
+}
+
+/// Breakpoints, watchpoints and single stepping on top of `Chip8`.
+///
+/// Timers only tick when a whole frame runs freely, so they stay frozen
+/// while single stepping, however many instructions a step covers.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    read_watchpoints: BTreeSet<usize>,
+    write_watchpoints: BTreeSet<usize>,
+    register_watchpoints: Vec<Register>,
+    paused: bool,
+    steps: u32,
+    resume_from: Option<u16>,
+    console: Option<Receiver<String>>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads commands from stdin on a background thread. Execution starts paused.
+    pub fn with_console(mut self) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        self.console = Some(receiver);
+        self.paused = true;
+        println!("{HELP}");
+        prompt();
+        self
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.steps = 0;
+    }
+
+    pub fn resume(&mut self, pc: u16) {
+        self.paused = false;
+        self.resume_from = Some(pc);
+    }
+
+    pub fn step(&mut self, pc: u16, count: u32) {
+        self.paused = true;
+        self.steps = count;
+        self.resume_from = Some(pc);
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn watch_read(&mut self, addr: usize) {
+        self.read_watchpoints.insert(addr);
+    }
+
+    pub fn watch_write(&mut self, addr: usize) {
+        self.write_watchpoints.insert(addr);
+    }
+
+    pub fn watch_register(&mut self, register: Register) {
+        if !self.register_watchpoints.contains(&register) {
+            self.register_watchpoints.push(register);
+        }
+    }
+
+    /// Runs up to `cycles_per_frame` instructions, stopping early on a
+    /// breakpoint, watchpoint or the end of a single step.
+    pub fn run_frame(
+        &mut self,
+        chip: &mut Chip8,
+        keyboard: &Keyboard,
+        cycles_per_frame: u32,
+    ) -> Result<DebugFrame, ChipErrors> {
+        let mut frame = DebugFrame::default();
+        if self.paused && self.steps == 0 {
+            return Ok(frame);
+        }
+
+        for _ in 0..cycles_per_frame {
+            let pc = chip.pc();
+            if self.resume_from.take() != Some(pc) && self.breakpoints.contains(&pc) {
+                frame.stopped = Some(StopReason::Breakpoint(pc));
+                break;
+            }
+
+            let before: Vec<u16> = self
+                .register_watchpoints
+                .iter()
+                .map(|register| register.read(chip))
+                .collect();
+
+            frame.draw_update |= chip.emulateCycle(keyboard)?.draw_update;
+
+            frame.stopped = self.check_watchpoints(chip, &before);
+            if frame.stopped.is_none() && self.paused {
+                self.steps -= 1;
+                if self.steps == 0 {
+                    frame.stopped = Some(StopReason::Step);
+                }
+            }
+
+            if frame.stopped.is_some() || chip.waits_for_display() {
+                break;
+            }
+        }
+
+        match &frame.stopped {
+            Some(reason) => {
+                self.pause();
+                println!("Stopped: {reason}");
+                println!("{}", describe(chip));
+                prompt();
+            }
+            None if self.paused => {}
+            None => chip.tick_timers(),
+        }
+
+        Ok(frame)
+    }
+
+    fn check_watchpoints(&self, chip: &Chip8, before: &[u16]) -> Option<StopReason> {
+        for access in chip.last_accesses() {
+            let watchpoints = match access.kind {
+                AccessKind::Read => &self.read_watchpoints,
+                AccessKind::Write => &self.write_watchpoints,
+            };
+
+            if let Some(&addr) = watchpoints.range(access.range.clone()).next() {
+                return Some(match access.kind {
+                    AccessKind::Read => StopReason::MemoryRead(addr),
+                    AccessKind::Write => StopReason::MemoryWrite(addr),
+                });
+            }
+        }
+
+        self.register_watchpoints
+            .iter()
+            .zip(before)
+            .find_map(|(register, &old)| {
+                let new = register.read(chip);
+                (new != old).then_some(StopReason::RegisterChanged {
+                    register: *register,
+                    old,
+                    new,
+                })
+            })
+    }
+
+    /// Executes any commands typed at the console. Returns true if the user asked to quit.
+    pub fn poll_console(&mut self, chip: &Chip8) -> bool {
+        let Some(console) = &self.console else {
+            return false;
+        };
+
+        let mut lines = Vec::new();
+        loop {
+            match console.try_recv() {
+                Ok(line) => lines.push(line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.console = None;
+                    break;
+                }
+            }
+        }
+
+        for line in lines {
+            match self.execute(&line, chip) {
+                Ok(Some(output)) => println!("{output}"),
+                Ok(None) => return true,
+                Err(err) => println!("{err}"),
+            }
+
+            if self.paused && self.steps == 0 {
+                prompt();
+            }
+        }
+
+        false
+    }
+
+    /// Runs one console command. Returns `Ok(None)` for quit, otherwise text to show.
+    pub fn execute(&mut self, line: &str, chip: &Chip8) -> Result<Option<String>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let output = match words.as_slice() {
+            [] => String::new(),
+            ["s" | "step"] => {
+                self.step(chip.pc(), 1);
+                String::new()
+            }
+            ["s" | "step", count] => {
+                let count = count
+                    .parse()
+                    .map_err(|_| format!("Invalid step count {count}"))?;
+                self.step(chip.pc(), count);
+                String::new()
+            }
+            ["c" | "continue"] => {
+                self.resume(chip.pc());
+                "Continuing".to_string()
+            }
+            ["p" | "pause"] => {
+                self.pause();
+                describe(chip)
+            }
+            ["b" | "break", addr] => {
+                let addr = parse_pc(addr)?;
+                self.add_breakpoint(addr);
+                format!("Breakpoint set at {addr:04x}")
+            }
+            ["d" | "delete", addr] => {
+                let addr = parse_pc(addr)?;
+                self.remove_breakpoint(addr);
+                format!("Breakpoint removed from {addr:04x}")
+            }
+            ["w" | "watch", "read", addr] => {
+                let addr = parse_address(addr)?;
+                self.watch_read(addr);
+                format!("Watching reads of {addr:04x}")
+            }
+            ["w" | "watch", "write", addr] => {
+                let addr = parse_address(addr)?;
+                self.watch_write(addr);
+                format!("Watching writes to {addr:04x}")
+            }
+            ["w" | "watch", "reg", register] => {
+                let register: Register = register.parse()?;
+                self.watch_register(register);
+                format!("Watching {register}")
+            }
+            ["u" | "unwatch", "read", addr] => {
+                self.read_watchpoints.remove(&parse_address(addr)?);
+                String::new()
+            }
+            ["u" | "unwatch", "write", addr] => {
+                self.write_watchpoints.remove(&parse_address(addr)?);
+                String::new()
+            }
+            ["u" | "unwatch", "reg", register] => {
+                let register: Register = register.parse()?;
+                self.register_watchpoints
+                    .retain(|watched| *watched != register);
+                String::new()
+            }
+            ["l" | "list"] => self.describe_points(),
+            ["r" | "regs"] => describe(chip),
+            ["m" | "mem", addr] => dump_memory(chip, parse_address(addr)?, 16),
+            ["m" | "mem", addr, len] => {
+                let len = len.parse().map_err(|_| format!("Invalid length {len}"))?;
+                dump_memory(chip, parse_address(addr)?, len)
+            }
+            ["q" | "quit"] => return Ok(None),
+            ["h" | "help"] => HELP.to_string(),
+            _ => return Err(format!("Unknown command: {line}. Type help for a list.")),
+        };
+
+        Ok(Some(output))
+    }
+
+    fn describe_points(&self) -> String {
+        let mut lines = Vec::new();
+        for addr in &self.breakpoints {
+            lines.push(format!("break {addr:04x}"));
+        }
+        for addr in &self.read_watchpoints {
+            lines.push(format!("watch read {addr:04x}"));
+        }
+        for addr in &self.write_watchpoints {
+            lines.push(format!("watch write {addr:04x}"));
+        }
+        for register in &self.register_watchpoints {
+            lines.push(format!("watch reg {register}"));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Formats V0-VF, I, PC, SP, the stack and the timers.
+pub fn describe(chip: &Chip8) -> String {
+    let registers = chip.registers();
+    let row = |range: std::ops::Range<usize>| {
+        range
+            .map(|x| format!("V{x:X}={:02x}", registers[x]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let stack = chip
+        .stack()
+        .iter()
+        .map(|addr| format!("{addr:04x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let memory = chip.memory();
+    let pc = chip.pc() as usize;
+    let next = memory
+        .get(pc..pc + 2)
+        .map_or(0, |word| (word[0] as u16) << 8 | word[1] as u16);
+
+    format!(
+        "PC={:04x} [{:04x}] I={:04x} SP={:x} DT={:02x} ST={:02x}\n{}\n{}\nStack: [{}]",
+        chip.pc(),
+        next,
+        chip.index(),
+        chip.sp(),
+        chip.delay_timer(),
+        chip.sound_timer(),
+        row(0..8),
+        row(8..16),
+        stack,
+    )
+}
+
+fn dump_memory(chip: &Chip8, addr: usize, len: usize) -> String {
+    let memory = chip.memory();
+    let end = addr.saturating_add(len).min(memory.len());
+    let start = addr.min(end);
+
+    memory[start..end]
+        .chunks(16)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+            format!("{:04x}: {}", start + row * 16, bytes.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses a hexadecimal address, with or without a `0x` or `$` prefix.
+fn parse_address(text: &str) -> Result<usize, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+    usize::from_str_radix(digits, 16).map_err(|_| format!("Invalid address {text}"))
+}
+
+/// Parses an address the program counter can hold.
+fn parse_pc(text: &str) -> Result<u16, String> {
+    u16::try_from(parse_address(text)?)
+        .map_err(|_| format!("Address {text} is past the end of memory"))
+}
+
+fn prompt() {
+    print!("(chip8) ");
+    let _ = std::io::stdout().flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::quirks::Quirks;
+
+    /// `v0 := 60 delay := v0 loop again`.
+    fn chip() -> Chip8 {
+        Chip8::new(vec![0x60, 0x3C, 0xF0, 0x15, 0x12, 0x04], Quirks::default()).unwrap()
+    }
+
+    #[test]
+    fn timers_stay_frozen_across_a_long_step() {
+        let mut chip = chip();
+        let mut debugger = Debugger::new();
+        debugger.step(chip.pc(), 10);
+
+        let keyboard = Keyboard::new();
+        for _ in 0..4 {
+            debugger.run_frame(&mut chip, &keyboard, 2).unwrap();
+        }
+        assert_eq!(chip.delay_timer(), 60);
+
+        let frame = debugger.run_frame(&mut chip, &keyboard, 2).unwrap();
+        assert_eq!(frame.stopped, Some(StopReason::Step));
+        assert_eq!(chip.delay_timer(), 60);
+
+        debugger.resume(chip.pc());
+        debugger.run_frame(&mut chip, &keyboard, 2).unwrap();
+        assert_eq!(chip.delay_timer(), 59);
+    }
+
+    #[test]
+    fn rejects_breakpoints_past_the_end_of_memory() {
+        let chip = chip();
+        let mut debugger = Debugger::new();
+        assert!(debugger.execute("break 10200", &chip).is_err());
+        assert!(debugger.breakpoints.is_empty());
+
+        debugger.execute("break $ffff", &chip).unwrap();
+        assert_eq!(debugger.breakpoints, BTreeSet::from([0xFFFF]));
+    }
+
+    #[test]
+    fn memory_dumps_stop_at_the_end_of_memory() {
+        let chip = chip();
+        assert_eq!(dump_memory(&chip, 0xFFFE, usize::MAX), "fffe: 00 00");
+        assert_eq!(dump_memory(&chip, usize::MAX, 16), "");
+    }
+}
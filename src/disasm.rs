@@ -0,0 +1,393 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::models::{chip8::START_ADDRESS, opcode::Opcode};
+
+/// Maximum number of bytes per `db` directive.
+const DATA_BYTES_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteKind {
+    Data,
+    /// First byte of a decoded instruction.
+    Code,
+    /// Remaining bytes of a decoded instruction.
+    Operand,
+}
+
+/// Which bytes of a ROM are code, and which addresses get labels.
+struct Analysis<'a> {
+    rom: &'a [u8],
+    kinds: Vec<ByteKind>,
+    labels: BTreeSet<usize>,
+}
+
+/// Decodes a ROM loaded at 0x200 into an annotated listing.
+///
+/// Code is found by following jumps, calls and skips from the entry point.
+/// Everything that is never reached, including sprites, is emitted as `db`
+/// directives. Jump and call targets inside the ROM get `Lxxx` labels.
+pub fn disassemble(rom: &[u8]) -> String {
+    let analysis = analyse(rom);
+    let mut listing = String::new();
+    let mut addr = START_ADDRESS;
+    while addr < analysis.end() {
+        if analysis.labels.contains(&addr) {
+            let _ = writeln!(listing, "{}:", label(addr));
+        }
+
+        if let Some((opcode, word)) = analysis.instruction(addr) {
+            let len = instruction_length(opcode);
+            let (raw, text) = if len == 4 {
+                let operand = word_at(analysis.rom, addr + 2).unwrap_or_default();
+                (
+                    format!("{word:04X} {operand:04X}"),
+                    format!("LD I, 0x{operand:04X}"),
+                )
+            } else {
+                (format!("{word:04X}"), render(opcode, &analysis.labels))
+            };
+
+            let _ = writeln!(listing, "{addr:04X}  {raw:<9}  {text}");
+            addr += len;
+            continue;
+        }
+
+        let data = analysis.data_run(addr);
+        let values: Vec<String> = data.iter().map(|byte| format!("0x{byte:02X}")).collect();
+        let _ = writeln!(listing, "{addr:04X}  {:<9}  db {}", "", values.join(", "));
+        addr += data.len();
+    }
+
+    listing
+}
+
+/// Decodes a ROM loaded at 0x200 into Octo source that `asm::assemble`
+/// turns back into the same bytes.
+///
+/// Code and labels are found as in `disassemble`. Data is emitted as bare
+/// byte literals, and each line ends with a comment giving its address.
+pub fn disassemble_octo(rom: &[u8]) -> String {
+    let analysis = analyse(rom);
+    let mut source = String::new();
+    let mut addr = START_ADDRESS;
+    while addr < analysis.end() {
+        if analysis.labels.contains(&addr) {
+            let _ = writeln!(source, ": {}", label(addr));
+        }
+
+        let (text, len) = match analysis.instruction(addr) {
+            Some((opcode, _)) => {
+                let operand = word_at(analysis.rom, addr + 2).unwrap_or_default();
+                (
+                    render_octo(opcode, operand, &analysis.labels),
+                    instruction_length(opcode),
+                )
+            }
+            None => {
+                let data = analysis.data_run(addr);
+                let values: Vec<String> = data.iter().map(|byte| format!("0x{byte:02X}")).collect();
+                (values.join(" "), data.len())
+            }
+        };
+
+        let _ = writeln!(source, "\t{text:<40} # {addr:04X}");
+        addr += len;
+    }
+
+    source
+}
+
+/// Follows every path from the entry point to find the code.
+fn analyse(rom: &[u8]) -> Analysis<'_> {
+    let end = START_ADDRESS + rom.len();
+
+    let mut kinds = vec![ByteKind::Data; rom.len()];
+    let mut labels = BTreeSet::new();
+    let mut pending = vec![START_ADDRESS];
+    let add_label = |labels: &mut BTreeSet<usize>, target: u16| {
+        let target = target as usize;
+        if (START_ADDRESS..end).contains(&target) {
+            labels.insert(target);
+        }
+    };
+
+    while let Some(addr) = pending.pop() {
+        let Some(word) = word_at(rom, addr) else {
+            continue;
+        };
+        let Ok(opcode) = Opcode::parse(word) else {
+            continue;
+        };
+
+        let len = instruction_length(opcode);
+        let offset = addr - START_ADDRESS;
+        if addr + len > end
+            || kinds[offset..offset + len]
+                .iter()
+                .any(|kind| *kind != ByteKind::Data)
+        {
+            continue;
+        }
+
+        kinds[offset] = ByteKind::Code;
+        kinds[offset + 1..offset + len].fill(ByteKind::Operand);
+
+        let next = addr + len;
+        match opcode {
+            Opcode::Jump(target) => {
+                add_label(&mut labels, target);
+                pending.push(target as usize);
+            }
+            Opcode::CallSubroutine(target) => {
+                add_label(&mut labels, target);
+                pending.push(target as usize);
+                pending.push(next);
+            }
+            // The target depends on a register, so only the base gets a label.
+            Opcode::JumpPlus(target) => add_label(&mut labels, target),
+            Opcode::ReturnFromSubroutine | Opcode::Exit => {}
+            Opcode::SkipEqual(..)
+            | Opcode::SkipNonEqual(..)
+            | Opcode::SkipRegistersEqual(..)
+            | Opcode::SkipRegistersNonEqual(..)
+            | Opcode::SkipKeyEqual(..)
+            | Opcode::SkipKeyNonEqual(..) => {
+                let skipped = if word_at(rom, next) == Some(0xF000) {
+                    4
+                } else {
+                    2
+                };
+                pending.push(next);
+                pending.push(next + skipped);
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    // A jump into the middle of an instruction cannot be given a label.
+    labels.retain(|&addr| kinds[addr - START_ADDRESS] != ByteKind::Operand);
+
+    Analysis { rom, kinds, labels }
+}
+
+impl Analysis<'_> {
+    fn end(&self) -> usize {
+        START_ADDRESS + self.rom.len()
+    }
+
+    /// The instruction decoded at `addr`, and its first word, if `addr` is code.
+    fn instruction(&self, addr: usize) -> Option<(Opcode, u16)> {
+        if self.kinds[addr - START_ADDRESS] != ByteKind::Code {
+            return None;
+        }
+        let word = word_at(self.rom, addr).unwrap_or_default();
+        let opcode = Opcode::parse(word).expect("decoded during analysis");
+        Some((opcode, word))
+    }
+
+    /// The data bytes from `addr` up to the next code or label, at most
+    /// `DATA_BYTES_PER_LINE` of them.
+    fn data_run(&self, addr: usize) -> &[u8] {
+        let mut data_end = addr + 1;
+        while data_end < self.end()
+            && data_end - addr < DATA_BYTES_PER_LINE
+            && self.kinds[data_end - START_ADDRESS] != ByteKind::Code
+            && !self.labels.contains(&data_end)
+        {
+            data_end += 1;
+        }
+        &self.rom[addr - START_ADDRESS..data_end - START_ADDRESS]
+    }
+}
+
+/// The big-endian word at `addr`, if the ROM covers both bytes.
+fn word_at(rom: &[u8], addr: usize) -> Option<u16> {
+    let offset = addr.checked_sub(START_ADDRESS)?;
+    let bytes = rom.get(offset..offset.checked_add(2)?)?;
+    Some((bytes[0] as u16) << 8 | bytes[1] as u16)
+}
+
+fn instruction_length(opcode: Opcode) -> usize {
+    if opcode == Opcode::SetILong {
+        4
+    } else {
+        2
+    }
+}
+
+fn label(addr: usize) -> String {
+    format!("L{addr:03X}")
+}
+
+/// Formats an instruction, naming jump and call targets by their label.
+fn render(opcode: Opcode, labels: &BTreeSet<usize>) -> String {
+    let named = |target: u16| {
+        labels
+            .contains(&(target as usize))
+            .then(|| label(target as usize))
+    };
+
+    match opcode {
+        Opcode::Jump(target) => match named(target) {
+            Some(name) => format!("JP {name}"),
+            None => opcode.to_string(),
+        },
+        Opcode::JumpPlus(target) => match named(target) {
+            Some(name) => format!("JP V0, {name}"),
+            None => opcode.to_string(),
+        },
+        Opcode::CallSubroutine(target) => match named(target) {
+            Some(name) => format!("CALL {name}"),
+            None => opcode.to_string(),
+        },
+        _ => opcode.to_string(),
+    }
+}
+
+/// Formats an instruction as the Octo statement that assembles to it.
+/// `operand` is the word after it, the address loaded by `i := long`.
+fn render_octo(opcode: Opcode, operand: u16, labels: &BTreeSet<usize>) -> String {
+    let target = |addr: u16| {
+        if labels.contains(&(addr as usize)) {
+            label(addr as usize)
+        } else {
+            format!("0x{addr:03X}")
+        }
+    };
+
+    // Octo only skips through `if ... then`, which skips when the condition
+    // is false, so each skip is written with the opposite comparison.
+    match opcode {
+        Opcode::SetI(addr) => format!("i := {}", target(addr)),
+        Opcode::SetILong => format!("i := long 0x{operand:04X}"),
+        Opcode::SetVConstant(x, n) => format!("v{x:x} := 0x{n:02X}"),
+        Opcode::SetV(x, y) => format!("v{x:x} := v{y:x}"),
+        Opcode::ClearScreen => "clear".to_string(),
+        Opcode::ReturnFromSubroutine => "return".to_string(),
+        Opcode::CallSubroutine(addr) => format!(":call {}", target(addr)),
+        Opcode::Add(x, y) => format!("v{x:x} += v{y:x}"),
+        Opcode::Subtract(x, y) => format!("v{x:x} -= v{y:x}"),
+        Opcode::SubtractOpposite(x, y) => format!("v{x:x} =- v{y:x}"),
+        Opcode::ShiftLeft(x, y) => format!("v{x:x} <<= v{y:x}"),
+        Opcode::ShiftRight(x, y) => format!("v{x:x} >>= v{y:x}"),
+        Opcode::Or(x, y) => format!("v{x:x} |= v{y:x}"),
+        Opcode::And(x, y) => format!("v{x:x} &= v{y:x}"),
+        Opcode::Xor(x, y) => format!("v{x:x} ^= v{y:x}"),
+        Opcode::AddConstant(x, n) => format!("v{x:x} += 0x{n:02X}"),
+        Opcode::BinaryCodedDecimal(x) => format!("bcd v{x:x}"),
+        Opcode::SkipRegistersEqual(x, y) => format!("if v{x:x} != v{y:x} then"),
+        Opcode::SkipRegistersNonEqual(x, y) => format!("if v{x:x} == v{y:x} then"),
+        Opcode::SkipEqual(x, n) => format!("if v{x:x} != 0x{n:02X} then"),
+        Opcode::SkipNonEqual(x, n) => format!("if v{x:x} == 0x{n:02X} then"),
+        Opcode::SkipKeyEqual(x) => format!("if v{x:x} -key then"),
+        Opcode::SkipKeyNonEqual(x) => format!("if v{x:x} key then"),
+        Opcode::Draw(x, y, n) => format!("sprite v{x:x} v{y:x} {n}"),
+        Opcode::Jump(addr) => format!("jump {}", target(addr)),
+        Opcode::JumpPlus(addr) => format!("jump0 {}", target(addr)),
+        Opcode::SetDelayTimer(x) => format!("delay := v{x:x}"),
+        Opcode::GetDelayTimer(x) => format!("v{x:x} := delay"),
+        Opcode::SetSoundTimer(x) => format!("buzzer := v{x:x}"),
+        Opcode::Dump(x) => format!("save v{x:x}"),
+        Opcode::Load(x) => format!("load v{x:x}"),
+        Opcode::SpriteAddress(x) => format!("i := hex v{x:x}"),
+        Opcode::RandAnd(x, n) => format!("v{x:x} := random 0x{n:02X}"),
+        Opcode::AddMemory(x) => format!("i += v{x:x}"),
+        Opcode::GetKey(x) => format!("v{x:x} := key"),
+        Opcode::ScrollDown(n) => format!("scroll-down {n}"),
+        Opcode::ScrollRight => "scroll-right".to_string(),
+        Opcode::ScrollLeft => "scroll-left".to_string(),
+        Opcode::Exit => "exit".to_string(),
+        Opcode::LowResolution => "lores".to_string(),
+        Opcode::HighResolution => "hires".to_string(),
+        Opcode::BigSpriteAddress(x) => format!("i := bighex v{x:x}"),
+        Opcode::SaveFlags(x) => format!("saveflags v{x:x}"),
+        Opcode::LoadFlags(x) => format!("loadflags v{x:x}"),
+        Opcode::ScrollUp(n) => format!("scroll-up {n}"),
+        Opcode::SelectPlanes(n) => format!("plane {n}"),
+        Opcode::LoadAudioPattern => "audio".to_string(),
+        Opcode::SetPitch(x) => format!("pitch := v{x:x}"),
+        Opcode::SaveRange(x, y) => format!("save v{x:x} - v{y:x}"),
+        Opcode::LoadRange(x, y) => format!("load v{x:x} - v{y:x}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    fn rom(words: &[u16]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn bytes_after_an_unconditional_jump_are_data() {
+        // v0 := 5, jump over a sprite, then loop forever.
+        let listing = disassemble(&rom(&[0x6005, 0x1206, 0xF090, 0x1206]));
+        assert_eq!(
+            listing,
+            "\
+0200  6005       LD V0, 0x05
+0202  1206       JP L206
+0204             db 0xF0, 0x90
+L206:
+0206  1206       JP L206
+"
+        );
+    }
+
+    #[test]
+    fn jump_tables_get_a_label_but_stay_data() {
+        // v0 := 2, jump0 table. The entries are only reached through v0.
+        let listing = disassemble(&rom(&[0x6002, 0xB204, 0x1208, 0x120A, 0x00FD, 0x00FD]));
+        assert_eq!(
+            listing,
+            "\
+0200  6002       LD V0, 0x02
+0202  B204       JP V0, L204
+L204:
+0204             db 0x12, 0x08, 0x12, 0x0A, 0x00, 0xFD, 0x00, 0xFD
+"
+        );
+    }
+
+    #[test]
+    fn skips_reach_both_paths_and_jump_over_long_loads() {
+        let listing = disassemble(&rom(&[0x3001, 0xF000, 0x1234, 0x00FD]));
+        assert_eq!(
+            listing,
+            "\
+0200  3001       SE V0, 0x01
+0202  F000 1234  LD I, 0x1234
+0206  00FD       EXIT
+"
+        );
+    }
+
+    #[test]
+    fn octo_output_assembles_back_to_the_same_rom() {
+        let roms = [
+            rom(&[0x6005, 0x1206, 0xF090, 0x1206]),
+            rom(&[0x6002, 0xB204, 0x1208, 0x120A, 0x00FD, 0x00FD]),
+            rom(&[
+                0x00E0, 0x00C3, 0x00D2, 0x00FB, 0x00FC, 0x00FE, 0x00FF, 0x2220, 0x3A12, 0x4B34,
+                0x5AB0, 0x9AB0, 0xEA9E, 0xEAA1, 0x6AFF, 0x7A80, 0x8AB0, 0x8AB1, 0x8AB2, 0x8AB3,
+                0x8AB4, 0x8AB5, 0x8AB6, 0x8AB7, 0x8ABE, 0xA2A4, 0xCA0F, 0xDAB5, 0xFA07, 0xFA0A,
+                0xFA15, 0xFA18, 0xFA1E, 0xFA29, 0xFA30, 0xFA33, 0xFA55, 0xFA65, 0xFA75, 0xFA85,
+                0xF301, 0xF002, 0xFA3A, 0x5AB2, 0x5AB3, 0xF000, 0xABCD, 0x00FD, 0x00EE, 0xFF,
+            ]),
+        ];
+        for rom in roms {
+            let source = disassemble_octo(&rom);
+            assert_eq!(assemble(&source).unwrap(), rom, "{source}");
+        }
+    }
+
+    #[test]
+    fn octo_output_writes_skips_as_inverted_conditions() {
+        let source = disassemble_octo(&rom(&[0x3001, 0xE1A1, 0x00FD]));
+        let lines: Vec<&str> = source.lines().map(|line| line.trim()).collect();
+        assert!(lines[0].starts_with("if v0 != 0x01 then"), "{source}");
+        assert!(lines[1].starts_with("if v1 key then"), "{source}");
+    }
+}
@@ -0,0 +1,230 @@
+//! Rendering modes that blend recent frames, hiding the flicker of sprites
+//! that games erase and redraw with XOR. They only change what the display
+//! shows: the framebuffer, screenshots and videos are untouched.
+
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    models::{chip8::TIMER_FREQUENCY, framebuffer::Framebuffer},
+    palette::{Palette, Rgb},
+};
+
+/// Half-life of `phosphor` when none is given.
+pub const DEFAULT_HALF_LIFE: Duration = Duration::from_millis(50);
+/// Fading pixels dimmer than this are drawn as background.
+const MIN_GLOW: f32 = 1.0 / 64.0;
+
+/// How long pixels stay visible after they are erased.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Persistence {
+    /// Every frame is shown as it is.
+    #[default]
+    Off,
+    /// Erased pixels fade out like CRT phosphor, losing half their
+    /// brightness every `half_life`.
+    Phosphor { half_life: Duration },
+    /// Pixels stay lit while they were drawn in any of the last N frames.
+    MaxOf(u32),
+}
+
+/// Parses `off`, `phosphor`, `phosphor:HALF_LIFE_MS` or `max:FRAMES`.
+impl FromStr for Persistence {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let spec = spec.trim().to_ascii_lowercase();
+        let (mode, value) = match spec.split_once(':') {
+            Some((mode, value)) => (mode, Some(value)),
+            None => (spec.as_str(), None),
+        };
+        match (mode, value) {
+            ("off", None) => Ok(Self::Off),
+            ("phosphor", None) => Ok(Self::Phosphor {
+                half_life: DEFAULT_HALF_LIFE,
+            }),
+            ("phosphor", Some(millis)) => {
+                let millis = millis
+                    .parse()
+                    .with_context(|| format!("Invalid half-life {millis:?}, expected milliseconds"))?;
+                Ok(Self::Phosphor {
+                    half_life: Duration::from_millis(millis),
+                })
+            }
+            ("max", Some(frames)) => {
+                let frames = frames
+                    .parse()
+                    .with_context(|| format!("Invalid frame count {frames:?}"))?;
+                Ok(Self::MaxOf(frames))
+            }
+            _ => bail!(
+                "Unknown persistence mode {spec}, expected off, phosphor[:HALF_LIFE_MS] or max:FRAMES"
+            ),
+        }
+    }
+}
+
+/// What each pixel showed recently, for blending into the current frame.
+#[derive(Debug, Clone)]
+pub struct Afterglow {
+    persistence: Persistence,
+    width: usize,
+    height: usize,
+    /// The last value other than background each pixel had.
+    last_lit: Vec<u8>,
+    /// Frames since each pixel last had that value, 0 while it still does.
+    age: Vec<u32>,
+}
+
+impl Afterglow {
+    pub fn new(persistence: Persistence) -> Self {
+        Self {
+            persistence,
+            width: 0,
+            height: 0,
+            last_lit: Vec::new(),
+            age: Vec::new(),
+        }
+    }
+
+    /// Advances by one frame, in which the screen shows `gfx`.
+    pub fn update(&mut self, gfx: &Framebuffer) {
+        if (gfx.width(), gfx.height()) != (self.width, self.height) {
+            (self.width, self.height) = (gfx.width(), gfx.height());
+            self.clear();
+        }
+
+        for ((&pixel, last_lit), age) in gfx
+            .pixels()
+            .iter()
+            .zip(&mut self.last_lit)
+            .zip(&mut self.age)
+        {
+            if pixel != 0 {
+                *last_lit = pixel;
+                *age = 0;
+            } else {
+                *age = age.saturating_add(1);
+            }
+        }
+    }
+
+    /// Forgets every frame so far, such as after jumping to a saved state.
+    pub fn clear(&mut self) {
+        let len = self.width * self.height;
+        self.last_lit = vec![0; len];
+        self.age = vec![u32::MAX; len];
+    }
+
+    /// The colour of every pixel of `gfx` in `palette`, with erased pixels
+    /// still glowing in the colour they had.
+    pub fn colours(&self, gfx: &Framebuffer, palette: &Palette) -> Vec<Rgb> {
+        let current = gfx
+            .pixels()
+            .iter()
+            .map(|&pixel| palette.colours[pixel as usize]);
+        if (gfx.width(), gfx.height()) != (self.width, self.height) {
+            return current.collect();
+        }
+
+        let background = palette.colours[0];
+        current
+            .zip(gfx.pixels())
+            .zip(self.last_lit.iter().zip(&self.age))
+            .map(|((colour, &pixel), (&last_lit, &age))| {
+                let glow = self.glow(age);
+                if pixel != 0 || glow == 0.0 {
+                    return colour;
+                }
+                let lit = palette.colours[last_lit as usize];
+                std::array::from_fn(|channel| {
+                    let (from, to) = (background[channel] as f32, lit[channel] as f32);
+                    (from + (to - from) * glow).round() as u8
+                })
+            })
+            .collect()
+    }
+
+    /// Brightness, from 0 to 1, of a pixel erased `age` frames ago.
+    fn glow(&self, age: u32) -> f32 {
+        match self.persistence {
+            Persistence::Off => 0.0,
+            Persistence::Phosphor { half_life } => {
+                let half_life = half_life.as_secs_f32() * TIMER_FREQUENCY as f32;
+                let glow = 0.5f32.powf(age as f32 / half_life);
+                if glow >= MIN_GLOW {
+                    glow
+                } else {
+                    0.0
+                }
+            }
+            Persistence::MaxOf(frames) => {
+                if age < frames {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::framebuffer::{CHIP8_HEIGHT, CHIP8_WIDTH};
+
+    /// Colour of pixel (0, 0) over frames in which it is lit as `lit` says.
+    fn trace(persistence: Persistence, lit: &[bool]) -> Vec<Rgb> {
+        let palette = "classic".parse::<Palette>().unwrap();
+        let mut afterglow = Afterglow::new(persistence);
+        let mut gfx = Framebuffer::new(CHIP8_WIDTH, CHIP8_HEIGHT);
+        lit.iter()
+            .map(|&lit| {
+                if (gfx.get(0, 0) != 0) != lit {
+                    gfx.toggle(0, 0, 1);
+                }
+                afterglow.update(&gfx);
+                afterglow.colours(&gfx, &palette)[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn max_of_frames_bridges_short_gaps() {
+        let colours = trace(Persistence::MaxOf(2), &[true, false, true, false, false]);
+        let white = [255; 3];
+        assert_eq!(colours, [white, white, white, white, [0; 3]]);
+    }
+
+    #[test]
+    fn phosphor_halves_every_half_life() {
+        let half_life = Duration::from_secs(2) / TIMER_FREQUENCY;
+        let colours = trace(
+            Persistence::Phosphor { half_life },
+            &[true, false, false, false, false],
+        );
+        for (colour, expected) in colours.iter().zip([255, 180, 128, 90, 64]) {
+            assert!(colour[0].abs_diff(expected) <= 1, "{colours:?}");
+        }
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("off".parse::<Persistence>().unwrap(), Persistence::Off);
+        assert_eq!(
+            "phosphor:100".parse::<Persistence>().unwrap(),
+            Persistence::Phosphor {
+                half_life: Duration::from_millis(100)
+            }
+        );
+        assert_eq!(
+            "Max:3".parse::<Persistence>().unwrap(),
+            Persistence::MaxOf(3)
+        );
+        for bad in ["max", "phosphor:fast", "blur"] {
+            assert!(bad.parse::<Persistence>().is_err(), "{bad}");
+        }
+    }
+}
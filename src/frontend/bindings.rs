@@ -0,0 +1,475 @@
+//! Host key bindings for the keypad and emulator hotkeys, loaded from TOML
+//! along with the screen palette.
+//!
+//! Every entry replaces the built-in binding of the same key or hotkey, and
+//! an empty list unbinds it. Sections under `roms`, keyed by the ROM's
+//! FNV-1a hash in hex, are applied on top for that ROM only. Host keys are
+//! named as in SDL (`W`, `Up`, `Space`, `F1`...), optionally prefixed with
+//! `Shift+`, `Ctrl+` or `Alt+`. The palette is a preset name or a list of
+//! `#RRGGBB` colours, as for `--palette`.
+//!
+//! ```toml
+//! palette = "amber"
+//!
+//! # AZERTY layout
+//! [keys]
+//! 4 = ["A"]
+//! 5 = ["Z"]
+//! 7 = ["Q"]
+//! a = ["W"]
+//!
+//! [hotkeys]
+//! pause = ["P", "Pause"]
+//! fast_forward = ["Tab"]
+//!
+//! [hotkeys.save_state]
+//! 1 = ["Shift+F1"]
+//!
+//! [roms.0123456789abcdef]
+//! palette = "#000000,#ffffff,#ff0000,#ffff00"
+//!
+//! [roms.0123456789abcdef.keys]
+//! 5 = ["Up"]
+//! 8 = ["Down"]
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{input::keyboard::Key, models::errors::ConfigError, palette::Palette};
+
+use super::Command;
+
+/// Number of save state slots with hotkeys.
+pub const SAVE_SLOTS: u8 = 9;
+
+/// Host keys of the COSMAC VIP keypad layout, indexed by CHIP-8 key.
+const DEFAULT_KEYS: [&str; 16] = [
+    "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+];
+
+/// What a host key does when pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Holds down a CHIP-8 key.
+    Key(Key),
+    /// Runs an emulator command once per press.
+    Command(Command),
+    /// Plays the game backwards while held.
+    Rewind,
+    /// Runs faster than real time while held.
+    FastForward,
+}
+
+/// A host key, named as in SDL, with the modifiers that must be held.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKey {
+    pub name: String,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl FromStr for HostKey {
+    type Err = ConfigError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut key = Self {
+            name: String::new(),
+            shift: false,
+            ctrl: false,
+            alt: false,
+        };
+
+        let mut rest = spec.trim();
+        // A trailing `+` is the name of the plus key, not a separator.
+        while let Some((modifier, name)) = rest.split_once('+').filter(|(_, name)| !name.is_empty())
+        {
+            match modifier.trim().to_ascii_lowercase().as_str() {
+                "shift" => key.shift = true,
+                "ctrl" | "control" => key.ctrl = true,
+                "alt" => key.alt = true,
+                _ => return Err(invalid(&format!("unknown modifier in {spec:?}"))),
+            }
+            rest = name.trim();
+        }
+
+        if rest.is_empty() {
+            return Err(invalid(&format!("empty key name in {spec:?}")));
+        }
+        key.name = rest.to_string();
+        Ok(key)
+    }
+}
+
+impl fmt::Display for HostKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, modifier) in [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+        ] {
+            if held {
+                write!(f, "{modifier}+")?;
+            }
+        }
+        f.write_str(&self.name)
+    }
+}
+
+/// The host keys bound to each keypad key and hotkey.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bindings {
+    entries: Vec<(Action, Vec<HostKey>)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Self {
+            entries: Vec::new(),
+        };
+        let mut bind = |action, spec: &str| {
+            let key = spec.parse().expect("built-in bindings are valid");
+            bindings.set(action, vec![key]);
+        };
+
+        for (code, name) in (0..).zip(DEFAULT_KEYS) {
+            bind(Action::Key(Key::from_code(code).expect("16 keys")), name);
+        }
+        bind(Action::Command(Command::Quit), "Escape");
+        bind(Action::Command(Command::ToggleMute), "M");
+        bind(Action::Command(Command::TogglePause), "P");
+        bind(Action::Command(Command::Reset), "F10");
+        bind(Action::Command(Command::ToggleVideo), "G");
+        bind(Action::Command(Command::CyclePalette), "H");
+        bind(Action::Rewind, "Backspace");
+        bind(Action::FastForward, "Tab");
+        for slot in 1..=SAVE_SLOTS {
+            bind(
+                Action::Command(Command::LoadState(slot)),
+                &format!("F{slot}"),
+            );
+            bind(
+                Action::Command(Command::SaveState(slot)),
+                &format!("Shift+F{slot}"),
+            );
+        }
+
+        bindings
+    }
+}
+
+/// Settings read from the config file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub bindings: Bindings,
+    /// Palette to start with, if the file picks one.
+    pub palette: Option<Palette>,
+}
+
+impl Config {
+    /// Reads the config for the ROM with hash `rom_hash` from `path`, or
+    /// from `default_config_path` if that exists. Falls back to the built-in
+    /// layout.
+    pub fn load(path: Option<&Path>, rom_hash: u64) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_config_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        Self::from_toml(&text, rom_hash).with_context(|| format!("In {}", path.display()))
+    }
+
+    /// The built-in settings, overridden by the TOML config `text` and then
+    /// by its section for the ROM with hash `rom_hash`, if any.
+    pub fn from_toml(text: &str, rom_hash: u64) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(text).map_err(|err| invalid(err.message()))?;
+
+        let mut config = Self::default();
+        config.apply(&file.palette, &file.keys, &file.hotkeys)?;
+        for (hash, rom) in &file.roms {
+            let hash = u64::from_str_radix(hash, 16)
+                .map_err(|_| invalid(&format!("ROM hash {hash:?} is not hexadecimal")))?;
+            if hash == rom_hash {
+                config.apply(&rom.palette, &rom.keys, &rom.hotkeys)?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn apply(
+        &mut self,
+        palette: &Option<String>,
+        keys: &BTreeMap<String, Vec<String>>,
+        hotkeys: &HotkeysConfig,
+    ) -> Result<(), ConfigError> {
+        if let Some(palette) = palette {
+            self.palette = Some(palette.parse()?);
+        }
+        self.bindings.apply(keys, hotkeys)
+    }
+}
+
+impl Bindings {
+    /// The built-in bindings, overridden by the TOML config `text` and then
+    /// by its section for the ROM with hash `rom_hash`, if any.
+    pub fn from_toml(text: &str, rom_hash: u64) -> Result<Self, ConfigError> {
+        Ok(Config::from_toml(text, rom_hash)?.bindings)
+    }
+
+    /// Every host key with the action it triggers.
+    pub fn iter(&self) -> impl Iterator<Item = (&HostKey, Action)> {
+        self.entries
+            .iter()
+            .flat_map(|(action, keys)| keys.iter().map(move |key| (key, *action)))
+    }
+
+    /// The host keys bound to `action`.
+    pub fn keys_for(&self, action: Action) -> &[HostKey] {
+        self.entries
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys)
+    }
+
+    fn set(&mut self, action: Action, keys: Vec<HostKey>) {
+        match self.entries.iter_mut().find(|(bound, _)| *bound == action) {
+            Some((_, bound)) => *bound = keys,
+            None => self.entries.push((action, keys)),
+        }
+    }
+
+    fn apply(
+        &mut self,
+        keys: &BTreeMap<String, Vec<String>>,
+        hotkeys: &HotkeysConfig,
+    ) -> Result<(), ConfigError> {
+        for (code, specs) in keys {
+            let key = u8::from_str_radix(code, 16)
+                .ok()
+                .and_then(Key::from_code)
+                .ok_or_else(|| invalid(&format!("{code:?} is not a keypad key 0-F")))?;
+            self.set(Action::Key(key), parse_keys(specs)?);
+        }
+
+        let single = [
+            (Action::Command(Command::Quit), &hotkeys.quit),
+            (Action::Command(Command::ToggleMute), &hotkeys.mute),
+            (Action::Command(Command::TogglePause), &hotkeys.pause),
+            (Action::Command(Command::Reset), &hotkeys.reset),
+            (Action::Command(Command::ToggleVideo), &hotkeys.video),
+            (Action::Command(Command::CyclePalette), &hotkeys.palette),
+            (Action::Rewind, &hotkeys.rewind),
+            (Action::FastForward, &hotkeys.fast_forward),
+        ];
+        for (action, specs) in single {
+            if let Some(specs) = specs {
+                self.set(action, parse_keys(specs)?);
+            }
+        }
+
+        let slots = [
+            (Command::SaveState as fn(u8) -> Command, &hotkeys.save_state),
+            (Command::LoadState, &hotkeys.load_state),
+        ];
+        for (command, slots) in slots {
+            for (slot, specs) in slots {
+                let slot = slot
+                    .parse()
+                    .ok()
+                    .filter(|slot| (1..=SAVE_SLOTS).contains(slot))
+                    .ok_or_else(|| invalid(&format!("{slot:?} is not a save slot 1-9")))?;
+                self.set(Action::Command(command(slot)), parse_keys(specs)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `chip-8/config.toml` in the user's config directory.
+pub fn default_config_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("chip-8").join("config.toml"))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    palette: Option<String>,
+    #[serde(default)]
+    keys: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    hotkeys: HotkeysConfig,
+    #[serde(default)]
+    roms: BTreeMap<String, RomConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RomConfig {
+    palette: Option<String>,
+    #[serde(default)]
+    keys: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    hotkeys: HotkeysConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HotkeysConfig {
+    quit: Option<Vec<String>>,
+    mute: Option<Vec<String>>,
+    pause: Option<Vec<String>>,
+    reset: Option<Vec<String>>,
+    video: Option<Vec<String>>,
+    palette: Option<Vec<String>>,
+    rewind: Option<Vec<String>>,
+    fast_forward: Option<Vec<String>>,
+    #[serde(default)]
+    save_state: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    load_state: BTreeMap<String, Vec<String>>,
+}
+
+fn parse_keys(specs: &[String]) -> Result<Vec<HostKey>, ConfigError> {
+    specs.iter().map(|spec| spec.parse()).collect()
+}
+
+fn invalid(reason: &str) -> ConfigError {
+    ConfigError::InvalidBindings(reason.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(bindings: &Bindings, action: Action) -> Vec<String> {
+        bindings
+            .keys_for(action)
+            .iter()
+            .map(HostKey::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn defaults_use_the_vip_layout() {
+        let bindings = Bindings::default();
+        assert_eq!(names(&bindings, Action::Key(Key::KeyC)), ["4"]);
+        assert_eq!(names(&bindings, Action::Key(Key::Key0)), ["X"]);
+        assert_eq!(
+            names(&bindings, Action::Command(Command::SaveState(3))),
+            ["Shift+F3"]
+        );
+    }
+
+    #[test]
+    fn config_replaces_and_unbinds_entries() {
+        let text = r#"
+            [keys]
+            5 = ["Z", "Up"]
+            a = []
+
+            [hotkeys]
+            pause = ["Ctrl+P"]
+
+            [hotkeys.load_state]
+            2 = ["F12"]
+        "#;
+        let bindings = Bindings::from_toml(text, 0).unwrap();
+        assert_eq!(names(&bindings, Action::Key(Key::Key5)), ["Z", "Up"]);
+        assert!(names(&bindings, Action::Key(Key::KeyA)).is_empty());
+        assert_eq!(names(&bindings, Action::Key(Key::Key4)), ["Q"]);
+        assert_eq!(
+            names(&bindings, Action::Command(Command::TogglePause)),
+            ["Ctrl+P"]
+        );
+        assert_eq!(
+            names(&bindings, Action::Command(Command::LoadState(2))),
+            ["F12"]
+        );
+    }
+
+    #[test]
+    fn rom_section_applies_only_to_its_rom() {
+        let text = r#"
+            [keys]
+            5 = ["Z"]
+
+            [roms.00000000000000ff.keys]
+            5 = ["Space"]
+        "#;
+        let other = Bindings::from_toml(text, 0xFE).unwrap();
+        assert_eq!(names(&other, Action::Key(Key::Key5)), ["Z"]);
+
+        let rom = Bindings::from_toml(text, 0xFF).unwrap();
+        assert_eq!(names(&rom, Action::Key(Key::Key5)), ["Space"]);
+    }
+
+    #[test]
+    fn palette_comes_from_the_rom_section_first() {
+        let text = r##"
+            palette = "amber"
+
+            [roms.00000000000000ff]
+            palette = "#000000,#ffffff"
+        "##;
+        let other = Config::from_toml(text, 0xFE).unwrap();
+        assert_eq!(other.palette.unwrap().name, "amber");
+
+        let rom = Config::from_toml(text, 0xFF).unwrap();
+        assert_eq!(rom.palette.unwrap().colours[1], [255, 255, 255]);
+
+        assert!(matches!(
+            Config::from_toml("palette = \"sepia\"", 0),
+            Err(ConfigError::InvalidPalette(_))
+        ));
+    }
+
+    #[test]
+    fn host_key_modifiers() {
+        let key: HostKey = "ctrl+Shift+F1".parse().unwrap();
+        assert!(key.ctrl && key.shift && !key.alt);
+        assert_eq!(key.name, "F1");
+
+        let plus: HostKey = "Shift++".parse().unwrap();
+        assert_eq!(plus.name, "+");
+        assert!(plus.shift);
+
+        assert!("Hyper+A".parse::<HostKey>().is_err());
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        for text in [
+            "[keys]\ng = [\"A\"]",
+            "[hotkeys]\nturbo = [\"T\"]",
+            "[hotkeys.save_state]\n10 = [\"F10\"]",
+            "[roms.xyz.keys]\n1 = [\"A\"]",
+            "keys = 3",
+        ] {
+            assert!(
+                matches!(
+                    Bindings::from_toml(text, 0),
+                    Err(ConfigError::InvalidBindings(_))
+                ),
+                "{text}"
+            );
+        }
+    }
+}
@@ -0,0 +1,788 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    debugger::Debugger,
+    input::{keyboard::Keyboard, keypad::Keypad},
+    models::{
+        audio::AudioPattern,
+        chip8::{Chip8, TIMER_FREQUENCY},
+        errors::ChipErrors,
+        framebuffer::Framebuffer,
+    },
+    movie::{Movie, Playback},
+    palette::{Palette, Rgb},
+    rewind::Rewind,
+    video::{GifEncoder, VideoEncoder},
+    wav::WavWriter,
+};
+
+use self::afterglow::{Afterglow, Persistence};
+
+pub mod afterglow;
+pub mod bindings;
+pub mod null;
+pub mod options;
+pub mod script;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+
+/// How far behind the wall clock we may fall before giving up on catching up.
+const MAX_FRAME_LAG: u32 = 5;
+/// Speed-up while the fast-forward hotkey is held.
+const FAST_FORWARD_SPEED: u32 = 4;
+/// Pixel size of GIFs started with the video hotkey, unless configured.
+pub const DEFAULT_VIDEO_SCALE: usize = 4;
+
+/// Something that can show the emulated screen.
+pub trait DisplaySink {
+    fn present(&mut self, gfx: &Framebuffer) -> Result<()>;
+
+    /// Called before the next `present` when the colours change.
+    fn set_palette(&mut self, _palette: &Palette) {}
+
+    /// Shows `gfx` with every pixel in the colour given in `colours`, row by
+    /// row, for the persistence modes. Displays that cannot show arbitrary
+    /// colours fall back to `present`.
+    fn present_blended(&mut self, gfx: &Framebuffer, _colours: &[Rgb]) -> Result<()> {
+        self.present(gfx)
+    }
+
+    /// Called once when emulation stops on an error. The last frame stays visible.
+    fn show_crash(&mut self, err: &ChipErrors) -> Result<()> {
+        self.show_message(&format!("Emulator crashed: {err}"));
+        Ok(())
+    }
+
+    /// Shows a status line such as "Paused" or "Saved state to ...". Goes to
+    /// stderr unless the display has somewhere better to put it.
+    fn show_message(&mut self, message: &str) {
+        eprintln!("{message}");
+    }
+}
+
+/// Something that can play the sound timer tone.
+pub trait AudioSink {
+    /// Called once per frame with the current sound state.
+    fn update(&mut self, sound_active: bool, pattern: Option<AudioPattern>);
+
+    /// Returns whether the sound is now muted, or `None` if there is no
+    /// sound to mute.
+    fn toggle_mute(&mut self) -> Option<bool> {
+        None
+    }
+}
+
+/// Emulator hotkeys that are not part of the CHIP-8 keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    ToggleMute,
+    TogglePause,
+    /// Returns to the state emulation started from.
+    Reset,
+    /// Starts or stops recording a GIF next to the ROM.
+    ToggleVideo,
+    /// Switches to the next palette.
+    CyclePalette,
+    SaveState(u8),
+    LoadState(u8),
+}
+
+/// Host input gathered for one frame.
+#[derive(Debug, Default)]
+pub struct InputState {
+    pub keyboard: Keyboard,
+    pub commands: Vec<Command>,
+    /// The rewind hotkey is held down.
+    pub rewind: bool,
+    /// The fast-forward hotkey is held down.
+    pub fast_forward: bool,
+}
+
+/// Something that can provide the keypad state and hotkeys.
+pub trait InputSource {
+    fn poll(&mut self) -> Result<InputState>;
+}
+
+/// Embedders can feed `key_down` and `key_up` events through `Emulator::input`.
+impl InputSource for Keypad {
+    fn poll(&mut self) -> Result<InputState> {
+        Ok(InputState {
+            keyboard: self.take_frame(),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOutcome {
+    Running,
+    Crashed,
+    Halted,
+    Quit,
+}
+
+/// Drives a `Chip8` one frame at a time against pluggable backends.
+pub struct Emulator<D, A, I> {
+    pub chip: Chip8,
+    pub display: D,
+    pub audio: A,
+    pub input: I,
+    cycles_per_frame: u32,
+    crashed: bool,
+    paused: bool,
+    fast_forward: bool,
+    /// Snapshot taken at construction, restored by `Command::Reset`.
+    initial_state: Vec<u8>,
+    debugger: Option<Debugger>,
+    rom_path: Option<PathBuf>,
+    rewind: Option<Rewind>,
+    recording: Option<Movie>,
+    playback: Option<Playback>,
+    videos: Vec<Video>,
+    video_scale: usize,
+    audio_recording: Option<AudioRecording>,
+    /// The palettes `Command::CyclePalette` steps through, and the one shown.
+    palettes: Vec<Palette>,
+    palette: usize,
+    afterglow: Option<Afterglow>,
+}
+
+/// A video being recorded and the file it goes to.
+struct Video {
+    path: PathBuf,
+    encoder: Box<dyn VideoEncoder>,
+}
+
+/// The sound output being captured and the file it goes to.
+struct AudioRecording {
+    path: PathBuf,
+    writer: WavWriter<BufWriter<File>>,
+}
+
+impl<D: DisplaySink, A: AudioSink, I: InputSource> Emulator<D, A, I> {
+    pub fn new(chip: Chip8, display: D, audio: A, input: I, cycles_per_frame: u32) -> Self {
+        let initial_state = chip.save_state();
+        Self {
+            chip,
+            display,
+            audio,
+            input,
+            cycles_per_frame,
+            crashed: false,
+            paused: false,
+            fast_forward: false,
+            initial_state,
+            debugger: None,
+            rom_path: None,
+            rewind: None,
+            recording: None,
+            playback: None,
+            videos: Vec::new(),
+            video_scale: DEFAULT_VIDEO_SCALE,
+            audio_recording: None,
+            palettes: Palette::presets(),
+            palette: 0,
+            afterglow: None,
+        }
+    }
+
+    /// Runs every frame under `debugger`, honouring its breakpoints and console.
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Enables save state slots, stored next to the ROM as `<rom>.state<N>`.
+    pub fn with_save_slots(mut self, rom_path: impl Into<PathBuf>) -> Self {
+        self.rom_path = Some(rom_path.into());
+        self
+    }
+
+    /// Records every frame into `rewind`, so holding the rewind hotkey plays
+    /// the game backwards.
+    pub fn with_rewind(mut self, rewind: Rewind) -> Self {
+        self.rewind = Some(rewind);
+        self
+    }
+
+    /// Records the keypad of every emulated frame into `movie`.
+    pub fn with_recording(mut self, movie: Movie) -> Self {
+        self.recording = Some(movie);
+        self
+    }
+
+    /// Feeds the keypad from a movie instead of the input source until it
+    /// ends, then reports whether the run stayed in sync.
+    pub fn with_playback(mut self, playback: Playback) -> Self {
+        self.playback = Some(playback);
+        self
+    }
+
+    /// Records every emulated frame into `encoder`, written to `path`.
+    pub fn with_video(mut self, path: impl Into<PathBuf>, encoder: Box<dyn VideoEncoder>) -> Self {
+        self.videos.push(Video {
+            path: path.into(),
+            encoder,
+        });
+        self
+    }
+
+    /// Shows the screen in `palette`, which joins the cycle if it is not a preset.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = match self
+            .palettes
+            .iter()
+            .position(|preset| preset.colours == palette.colours)
+        {
+            Some(index) => index,
+            None => {
+                self.palettes.insert(0, palette);
+                0
+            }
+        };
+        self.display.set_palette(&self.palettes[self.palette]);
+        self
+    }
+
+    /// Blends recent frames on the display as `persistence` says, so that
+    /// sprites redrawn every frame do not flicker.
+    pub fn with_persistence(mut self, persistence: Persistence) -> Self {
+        self.afterglow = (persistence != Persistence::Off).then(|| Afterglow::new(persistence));
+        self
+    }
+
+    /// Pixel size of the GIFs started with `Command::ToggleVideo`.
+    pub fn with_video_scale(mut self, scale: usize) -> Self {
+        self.video_scale = scale;
+        self
+    }
+
+    /// Captures the sound output of every emulated frame into `writer`,
+    /// written to `path`.
+    pub fn with_wav(
+        mut self,
+        path: impl Into<PathBuf>,
+        writer: WavWriter<BufWriter<File>>,
+    ) -> Self {
+        self.audio_recording = Some(AudioRecording {
+            path: path.into(),
+            writer,
+        });
+        self
+    }
+
+    /// Completes every video and audio file being recorded.
+    pub fn finish_recordings(&mut self) -> Result<()> {
+        self.stop_videos()?;
+        if let Some(mut recording) = self.audio_recording.take() {
+            recording
+                .writer
+                .finish()
+                .with_context(|| format!("Cannot write {}", recording.path.display()))?;
+            self.display
+                .show_message(&format!("Saved audio to {}", recording.path.display()));
+        }
+        Ok(())
+    }
+
+    fn stop_videos(&mut self) -> Result<()> {
+        for mut video in self.videos.drain(..) {
+            video
+                .encoder
+                .finish()
+                .with_context(|| format!("Cannot write {}", video.path.display()))?;
+            self.display
+                .show_message(&format!("Saved video to {}", video.path.display()));
+        }
+        Ok(())
+    }
+
+    /// Stops recording and returns the movie, sealed with the current state.
+    pub fn take_recording(&mut self) -> Option<Movie> {
+        let mut movie = self.recording.take()?;
+        movie.finish(&self.chip);
+        Some(movie)
+    }
+
+    /// Polls input, runs one frame and presents the result.
+    pub fn step_frame(&mut self) -> Result<FrameOutcome> {
+        let mut input = self.input.poll()?;
+        for command in input.commands {
+            match command {
+                Command::Quit => return Ok(FrameOutcome::Quit),
+                Command::ToggleMute => {
+                    if let Some(muted) = self.audio.toggle_mute() {
+                        let state = if muted { "muted" } else { "unmuted" };
+                        self.display.show_message(&format!("Sound {state}"));
+                    }
+                }
+                Command::ToggleVideo => {
+                    if let Err(err) = self.toggle_video() {
+                        self.display
+                            .show_message(&format!("Cannot record video: {err:#}"));
+                    }
+                }
+                Command::CyclePalette => {
+                    self.palette = (self.palette + 1) % self.palettes.len();
+                    self.display.set_palette(&self.palettes[self.palette]);
+                    self.present()?;
+                    self.display
+                        .show_message(&format!("Palette: {}", self.palettes[self.palette]));
+                }
+                Command::TogglePause => {
+                    self.paused = !self.paused;
+                    self.display
+                        .show_message(if self.paused { "Paused" } else { "Resumed" });
+                }
+                Command::SaveState(slot) => {
+                    if let Err(err) = self.save_state(slot) {
+                        self.display
+                            .show_message(&format!("Cannot save state: {err:#}"));
+                    }
+                }
+                Command::LoadState(_) | Command::Reset
+                    if self.recording.is_some() || self.playback.is_some() =>
+                {
+                    self.display.show_message(
+                        "Cannot load state or reset while a movie is recording or playing",
+                    );
+                }
+                Command::Reset => {
+                    self.chip.load_state(&self.initial_state)?;
+                    self.crashed = false;
+                    self.forget_history();
+                    self.present()?;
+                }
+                Command::LoadState(slot) => match self.load_state(slot) {
+                    Ok(()) => {
+                        self.forget_history();
+                        self.present()?;
+                    }
+                    Err(err) => self
+                        .display
+                        .show_message(&format!("Cannot load state: {err:#}")),
+                },
+            }
+        }
+
+        if let Some(debugger) = &mut self.debugger {
+            if debugger.poll_console(&self.chip) {
+                return Ok(FrameOutcome::Quit);
+            }
+        }
+
+        self.fast_forward = input.fast_forward;
+        if self.paused {
+            self.audio.update(false, self.chip.audio_pattern());
+            return Ok(FrameOutcome::Running);
+        }
+
+        self.check_playback();
+        if let Some(playback) = &mut self.playback {
+            input.keyboard = playback.next_frame().unwrap_or_default();
+            input.rewind = false;
+        }
+
+        if input.rewind {
+            if let Some(rewind) = &mut self.rewind {
+                let frames = rewind.step_back(&mut self.chip)?;
+                if let Some(movie) = &mut self.recording {
+                    movie.truncate(frames as usize);
+                }
+                self.crashed = false;
+                self.update_afterglow();
+                self.present()?;
+                self.audio.update(false, self.chip.audio_pattern());
+                return Ok(FrameOutcome::Running);
+            }
+        }
+
+        if self.crashed {
+            return Ok(FrameOutcome::Crashed);
+        }
+
+        // No emulated time passes while the debugger is paused, so nothing
+        // records those frames; single steps do not tick the timers either.
+        let was_paused = self
+            .debugger
+            .as_ref()
+            .is_some_and(|debugger| debugger.is_paused());
+        if let Some(movie) = self.recording.as_mut().filter(|_| !was_paused) {
+            movie.record(&input.keyboard);
+        }
+
+        let draw_update = match self.run_chip_frame(&input.keyboard) {
+            Ok(draw_update) => draw_update,
+            Err(err) => {
+                self.display.show_crash(&err)?;
+                self.audio.update(false, None);
+                self.crashed = true;
+                self.check_playback();
+                return Ok(FrameOutcome::Crashed);
+            }
+        };
+
+        // Fading pixels change even when the screen does not.
+        self.update_afterglow();
+        if draw_update || self.afterglow.is_some() {
+            self.present()?;
+        }
+
+        if !was_paused {
+            if let Some(rewind) = &mut self.rewind {
+                rewind.record(&self.chip);
+            }
+            self.record_videos();
+        }
+        self.check_playback();
+
+        let paused = self
+            .debugger
+            .as_ref()
+            .is_some_and(|debugger| debugger.is_paused());
+        let sound_active = self.chip.sound_timer() > 0 && !paused;
+        if !was_paused {
+            self.record_audio(sound_active);
+        }
+        self.audio.update(sound_active, self.chip.audio_pattern());
+
+        if self.chip.is_halted() {
+            return Ok(FrameOutcome::Halted);
+        }
+
+        Ok(FrameOutcome::Running)
+    }
+
+    /// Shows the screen, blended with recent frames if persistence is on.
+    fn present(&mut self) -> Result<()> {
+        let gfx = self.chip.framebuffer();
+        match &self.afterglow {
+            Some(afterglow) => {
+                let colours = afterglow.colours(gfx, &self.palettes[self.palette]);
+                self.display.present_blended(gfx, &colours)
+            }
+            None => self.display.present(gfx),
+        }
+    }
+
+    fn update_afterglow(&mut self) {
+        if let Some(afterglow) = &mut self.afterglow {
+            afterglow.update(self.chip.framebuffer());
+        }
+    }
+
+    /// Drops the rewind buffer and afterglow after jumping to another
+    /// state, so neither leads back into the session before the jump.
+    fn forget_history(&mut self) {
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+        if let Some(afterglow) = &mut self.afterglow {
+            afterglow.clear();
+        }
+    }
+
+    /// Once the movie has run out, compares the final state with the one
+    /// recorded and hands control back to the input source.
+    fn check_playback(&mut self) {
+        if !self.playback.as_ref().is_some_and(Playback::is_finished) {
+            return;
+        }
+
+        let playback = self.playback.take().expect("checked above");
+        let frames = playback.movie().frames.len();
+        self.display.show_message(&if playback.in_sync(&self.chip) {
+            format!("Movie finished after {frames} frames, in sync")
+        } else {
+            format!("Movie desynced: final state differs from the recording after {frames} frames")
+        });
+    }
+
+    /// Stops the videos being recorded, or starts a GIF at the first free
+    /// `<rom>.video<N>.gif`.
+    fn toggle_video(&mut self) -> Result<()> {
+        if !self.videos.is_empty() {
+            return self.stop_videos();
+        }
+
+        let rom_path = self
+            .rom_path
+            .as_deref()
+            .context("Videos are saved next to the ROM, which is not known")?;
+        let path = (1..)
+            .map(|index| video_path(rom_path, index))
+            .find(|path| !path.exists())
+            .expect("some index is free");
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Cannot create {}", path.display()))?;
+        let encoder = GifEncoder::new(std::io::BufWriter::new(file), self.video_scale);
+        self.display
+            .show_message(&format!("Recording video to {}", path.display()));
+        self.videos.push(Video {
+            path,
+            encoder: Box::new(encoder),
+        });
+        Ok(())
+    }
+
+    /// Adds the current frame to every video, dropping those that fail.
+    fn record_videos(&mut self) {
+        let gfx = self.chip.framebuffer();
+        let colours = &self.palettes[self.palette].colours;
+        let display = &mut self.display;
+        self.videos
+            .retain_mut(|video| match video.encoder.push_frame(gfx, colours) {
+                Ok(()) => true,
+                Err(err) => {
+                    display.show_message(&format!(
+                        "Stopped recording {}: {err}",
+                        video.path.display()
+                    ));
+                    false
+                }
+            });
+    }
+
+    /// Adds the current frame's sound output to the WAV file, dropping it on failure.
+    fn record_audio(&mut self, sound_active: bool) {
+        let Some(recording) = &mut self.audio_recording else {
+            return;
+        };
+        let pattern = self.chip.audio_pattern();
+        if let Err(err) = recording.writer.push_frame(sound_active, pattern) {
+            self.display.show_message(&format!(
+                "Stopped recording {}: {err}",
+                recording.path.display()
+            ));
+            self.audio_recording = None;
+        }
+    }
+
+    fn slot_path(&self, slot: u8) -> Result<PathBuf> {
+        let rom_path = self
+            .rom_path
+            .as_deref()
+            .context("Save slots are not enabled")?;
+        Ok(state_path(rom_path, slot))
+    }
+
+    fn save_state(&mut self, slot: u8) -> Result<()> {
+        let path = self.slot_path(slot)?;
+        std::fs::write(&path, self.chip.save_state())
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+        self.display
+            .show_message(&format!("Saved state to {}", path.display()));
+        Ok(())
+    }
+
+    fn load_state(&mut self, slot: u8) -> Result<()> {
+        let path = self.slot_path(slot)?;
+        let data =
+            std::fs::read(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+        self.chip.load_state(&data)?;
+        self.crashed = false;
+        self.display
+            .show_message(&format!("Loaded state from {}", path.display()));
+        Ok(())
+    }
+
+    /// Runs one frame, through the debugger if one is attached. Returns
+    /// whether the screen changed.
+    fn run_chip_frame(&mut self, keyboard: &Keyboard) -> Result<bool, ChipErrors> {
+        match &mut self.debugger {
+            Some(debugger) => Ok(debugger
+                .run_frame(&mut self.chip, keyboard, self.cycles_per_frame)?
+                .draw_update),
+            None => Ok(self
+                .chip
+                .run_frame(keyboard, self.cycles_per_frame)?
+                .draw_update),
+        }
+    }
+
+    /// Runs frames at `TIMER_FREQUENCY`, paced against the wall clock, until
+    /// the user quits or the program exits.
+    pub fn run(&mut self) -> Result<()> {
+        let frame_duration = Duration::from_secs(1) / TIMER_FREQUENCY;
+        let mut next_frame = Instant::now();
+
+        loop {
+            match self.step_frame()? {
+                FrameOutcome::Running | FrameOutcome::Crashed => {}
+                FrameOutcome::Halted => {
+                    self.display.show_message("Program exited");
+                    return Ok(());
+                }
+                FrameOutcome::Quit => return Ok(()),
+            }
+
+            let frame_duration = if self.fast_forward {
+                frame_duration / FAST_FORWARD_SPEED
+            } else {
+                frame_duration
+            };
+            next_frame += frame_duration;
+            let now = Instant::now();
+            if next_frame > now {
+                std::thread::sleep(next_frame - now);
+            } else if now - next_frame > frame_duration * MAX_FRAME_LAG {
+                next_frame = now;
+            }
+        }
+    }
+}
+
+/// Path of the `index`th video recorded with the hotkey for the ROM at `rom_path`.
+pub fn video_path(rom_path: &Path, index: u32) -> PathBuf {
+    let mut name = rom_path.as_os_str().to_owned();
+    name.push(format!(".video{index}.gif"));
+    PathBuf::from(name)
+}
+
+/// Path of save slot `slot` for the ROM at `rom_path`.
+pub fn state_path(rom_path: &Path, slot: u8) -> PathBuf {
+    let mut name = rom_path.as_os_str().to_owned();
+    name.push(format!(".state{slot}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, collections::VecDeque, rc::Rc};
+
+    use super::*;
+    use crate::{frontend::null::Null, models::quirks::Quirks};
+
+    /// Hands out one prepared `InputState` per frame, then nothing.
+    struct Frames(VecDeque<InputState>);
+
+    impl InputSource for Frames {
+        fn poll(&mut self) -> Result<InputState> {
+            Ok(self.0.pop_front().unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn reset_forgets_the_rewind_history() {
+        // Counts frames in V0: `loop v0 += 1 again`.
+        let chip = Chip8::new(vec![0x70, 0x01, 0x12, 0x00], Quirks::default()).unwrap();
+        let reset = InputState {
+            commands: vec![Command::Reset],
+            ..Default::default()
+        };
+        let rewind = InputState {
+            rewind: true,
+            ..Default::default()
+        };
+        let mut inputs: VecDeque<_> = (0..10).map(|_| InputState::default()).collect();
+        inputs.extend([reset, rewind]);
+
+        let mut emulator =
+            Emulator::new(chip, Null, Null, Frames(inputs), 1).with_rewind(Rewind::new(1 << 20));
+        for _ in 0..10 {
+            emulator.step_frame().unwrap();
+        }
+        assert_eq!(emulator.chip.registers()[0], 5);
+
+        // Rewind stops at the first frame after the reset instead of going
+        // back into the old session.
+        emulator.step_frame().unwrap();
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.chip.registers()[0], 1);
+    }
+
+    /// Keeps every status line instead of showing it.
+    #[derive(Default)]
+    struct Messages(Vec<String>);
+
+    impl DisplaySink for Messages {
+        fn present(&mut self, _gfx: &Framebuffer) -> Result<()> {
+            Ok(())
+        }
+
+        fn show_message(&mut self, message: &str) {
+            self.0.push(message.to_string());
+        }
+    }
+
+    #[test]
+    fn status_lines_go_to_the_display() {
+        let chip = Chip8::new(vec![0x12, 0x00], Quirks::default()).unwrap();
+        let commands = InputState {
+            commands: vec![Command::TogglePause, Command::SaveState(1)],
+            ..Default::default()
+        };
+        let mut emulator = Emulator::new(
+            chip,
+            Messages::default(),
+            Null,
+            Frames(VecDeque::from([commands])),
+            1,
+        );
+        emulator.step_frame().unwrap();
+        assert_eq!(
+            emulator.display.0,
+            ["Paused", "Cannot save state: Save slots are not enabled"]
+        );
+    }
+
+    /// Counts the frames it is given, through a handle the test keeps.
+    struct CountingEncoder(Rc<Cell<usize>>);
+
+    impl VideoEncoder for CountingEncoder {
+        fn push_frame(&mut self, _: &Framebuffer, _: &[Rgb; 4]) -> std::io::Result<()> {
+            self.0.set(self.0.get() + 1);
+            Ok(())
+        }
+
+        fn finish(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn paused_debugger_frames_are_not_recorded() {
+        let rom = vec![0x70, 0x01, 0x12, 0x00];
+        let chip = Chip8::new(rom.clone(), Quirks::default()).unwrap();
+        let movie = Movie::new(&rom, &chip, 1);
+        let video_frames = Rc::new(Cell::new(0));
+        let wav_path =
+            std::env::temp_dir().join(format!("chip8-paused-{}.wav", std::process::id()));
+        let wav = WavWriter::new(
+            BufWriter::new(File::create(&wav_path).unwrap()),
+            8000,
+            440.0,
+        )
+        .unwrap();
+
+        let mut debugger = Debugger::new();
+        debugger.pause();
+        let mut emulator = Emulator::new(chip, Null, Null, Frames(VecDeque::new()), 1)
+            .with_rewind(Rewind::new(1 << 20))
+            .with_recording(movie)
+            .with_video("count", Box::new(CountingEncoder(video_frames.clone())))
+            .with_wav(&wav_path, wav)
+            .with_debugger(debugger);
+        for _ in 0..10 {
+            emulator.step_frame().unwrap();
+        }
+        emulator.finish_recordings().unwrap();
+
+        assert!(emulator.rewind.as_ref().unwrap().is_empty());
+        assert!(emulator.take_recording().unwrap().frames.is_empty());
+        assert_eq!(video_frames.get(), 0);
+        let wav_len = std::fs::metadata(&wav_path).unwrap().len();
+        std::fs::remove_file(&wav_path).unwrap();
+        assert_eq!(wav_len, 44, "only the header");
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::Result;
+
+use crate::models::{audio::AudioPattern, framebuffer::Framebuffer};
+
+use super::{AudioSink, DisplaySink, InputSource, InputState};
+
+/// Backend that discards output and never presses a key. Status lines go
+/// to stderr, leaving stdout to whatever the headless run reports.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Null;
+
+impl DisplaySink for Null {
+    fn present(&mut self, _gfx: &Framebuffer) -> Result<()> {
+        Ok(())
+    }
+
+    fn show_message(&mut self, message: &str) {
+        eprintln!("{message}");
+    }
+}
+
+impl AudioSink for Null {
+    fn update(&mut self, _sound_active: bool, _pattern: Option<AudioPattern>) {}
+}
+
+impl InputSource for Null {
+    fn poll(&mut self) -> Result<InputState> {
+        Ok(InputState::default())
+    }
+}
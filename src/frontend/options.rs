@@ -0,0 +1,183 @@
+//! Command-line options shared by the emulator binaries. Each binary matches
+//! its own flags first, hands the rest to `CommonOptions::parse_flag` and
+//! whatever is left over to `rom_path`.
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    frontend::afterglow::Persistence,
+    models::{chip8::TIMER_FREQUENCY, quirks::Quirks},
+    palette::Palette,
+};
+
+pub const DEFAULT_CYCLES_PER_FRAME: u32 = 10;
+pub const DEFAULT_REWIND_MB: usize = 16;
+
+/// Speed, compatibility, randomness, rewind and display options.
+#[derive(Debug, Clone)]
+pub struct CommonOptions {
+    pub cycles_per_frame: u32,
+    pub quirks: Quirks,
+    pub seed: Option<u64>,
+    pub rewind_mb: usize,
+    pub palette: Option<Palette>,
+    pub persistence: Persistence,
+    quirk_overrides: Vec<String>,
+}
+
+impl Default for CommonOptions {
+    fn default() -> Self {
+        Self {
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            quirks: Quirks::default(),
+            seed: None,
+            rewind_mb: DEFAULT_REWIND_MB,
+            palette: None,
+            persistence: Persistence::Off,
+            quirk_overrides: Vec::new(),
+        }
+    }
+}
+
+impl CommonOptions {
+    /// Parses `flag`, taking its value from `args`, if it is one of the
+    /// shared options. Returns false for any other argument.
+    pub fn parse_flag<'a>(
+        &mut self,
+        flag: &str,
+        args: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<bool> {
+        match flag {
+            "--ipf" => {
+                let value = args
+                    .next()
+                    .context("--ipf expects instructions per frame")?;
+                self.cycles_per_frame = value.parse().context("Invalid --ipf value")?;
+            }
+            "--hz" => {
+                let value = args
+                    .next()
+                    .context("--hz expects instructions per second")?;
+                let hz: u32 = value.parse().context("Invalid --hz value")?;
+                self.cycles_per_frame = (hz + TIMER_FREQUENCY / 2) / TIMER_FREQUENCY;
+            }
+            "--quirks" => {
+                let value = args.next().context(
+                    "--quirks expects one of legacy (the default), vip, chip48, schip, octo",
+                )?;
+                self.quirks = value.parse()?;
+            }
+            "--quirk" => {
+                let value = args.next().context("--quirk expects NAME=VALUE")?;
+                self.quirk_overrides.push(value.clone());
+            }
+            "--seed" => {
+                let value = args.next().context("--seed expects a number")?;
+                self.seed = Some(value.parse().context("Invalid --seed value")?);
+            }
+            "--rewind-mb" => {
+                let value = args
+                    .next()
+                    .context("--rewind-mb expects a size in MiB, 0 to disable")?;
+                self.rewind_mb = value.parse().context("Invalid --rewind-mb value")?;
+            }
+            "--palette" => {
+                let value = args
+                    .next()
+                    .context("--palette expects a preset name or #RRGGBB colours")?;
+                self.palette = Some(value.parse()?);
+            }
+            "--persistence" => {
+                let value = args
+                    .next()
+                    .context("--persistence expects off, phosphor[:MS] or max:FRAMES")?;
+                self.persistence = value.parse()?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Applies the `--quirk` overrides on top of the preset, wherever they
+    /// appeared, and runs at least one cycle per frame. Call once every
+    /// argument has been parsed.
+    pub fn finish(mut self) -> Result<Self> {
+        for spec in std::mem::take(&mut self.quirk_overrides) {
+            self.quirks.apply_override(&spec)?;
+        }
+        self.cycles_per_frame = self.cycles_per_frame.max(1);
+        Ok(self)
+    }
+}
+
+/// Takes `arg`, which no flag claimed, as the ROM path. An argument starting
+/// with `-` is an unknown flag, most likely a typo, so this prints `usage`
+/// and exits instead of trying to load it as a ROM.
+pub fn rom_path(arg: &str, usage: &str) -> String {
+    if let Err(err) = check_rom_path(arg) {
+        eprintln!("{err}\n{usage}");
+        std::process::exit(2);
+    }
+    arg.to_string()
+}
+
+fn check_rom_path(arg: &str) -> Result<()> {
+    if arg.starts_with('-') {
+        bail!("Unknown option {arg}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(CommonOptions, Vec<String>)> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut options = CommonOptions::default();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !options.parse_flag(arg, &mut args)? {
+                rest.push(arg.clone());
+            }
+        }
+        Ok((options.finish()?, rest))
+    }
+
+    #[test]
+    fn hz_rounds_to_the_nearest_cycle_count() {
+        let (options, _) = parse(&["--hz", "700"]).unwrap();
+        assert_eq!(options.cycles_per_frame, 12);
+        let (options, _) = parse(&["--hz", "10"]).unwrap();
+        assert_eq!(options.cycles_per_frame, 1);
+    }
+
+    #[test]
+    fn quirk_overrides_apply_after_the_preset() {
+        let (overridden, _) = parse(&["--quirk", "shifting=off", "--quirks", "schip"]).unwrap();
+        let (preset, _) = parse(&["--quirks", "schip"]).unwrap();
+        assert_ne!(overridden.quirks, preset.quirks);
+        assert_eq!(overridden.quirks, {
+            let mut quirks = preset.quirks;
+            quirks.apply_override("shifting=off").unwrap();
+            quirks
+        });
+    }
+
+    #[test]
+    fn leaves_other_arguments_alone() {
+        let (options, rest) = parse(&["--mute", "--seed", "7", "game.ch8"]).unwrap();
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(rest, ["--mute", "game.ch8"]);
+        assert!(parse(&["--ipf"]).is_err());
+    }
+
+    #[test]
+    fn unknown_flags_are_not_rom_paths() {
+        assert!(check_rom_path("game.ch8").is_ok());
+        assert!(check_rom_path("roms/IBM Logo.ch8").is_ok());
+        assert!(check_rom_path("--mtue").is_err());
+        assert!(check_rom_path("-x").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use crate::{input::keyboard::Keyboard, models::errors::ConfigError};
+
+use super::{InputSource, InputState};
+
+/// A keypad key held for a span of frames, written `KEY@FRAME[+FRAMES]`,
+/// e.g. `5@120` or `a@30+10`. Frames count from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub key: u8,
+    pub frame: u32,
+    pub frames: u32,
+}
+
+impl KeyPress {
+    fn is_held(&self, frame: u32) -> bool {
+        frame >= self.frame && frame - self.frame < self.frames
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = ConfigError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConfigError::InvalidKeyPress(spec.to_string());
+        let (key, timing) = spec.split_once('@').ok_or_else(invalid)?;
+        let (frame, frames) = timing.split_once('+').unwrap_or((timing, "1"));
+
+        let key = u8::from_str_radix(key, 16).map_err(|_| invalid())?;
+        if key > 0xF {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            key,
+            frame: frame.parse().map_err(|_| invalid())?,
+            frames: frames.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Input source that replays a fixed list of key presses, for unattended runs.
+#[derive(Debug, Default)]
+pub struct ScriptedInput {
+    presses: Vec<KeyPress>,
+    frame: u32,
+}
+
+impl ScriptedInput {
+    pub fn new(presses: Vec<KeyPress>) -> Self {
+        Self { presses, frame: 0 }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn poll(&mut self) -> Result<InputState> {
+        let mask = self
+            .presses
+            .iter()
+            .filter(|press| press.is_held(self.frame))
+            .fold(0, |mask, press| mask | 1 << press.key);
+        self.frame += 1;
+
+        Ok(InputState {
+            keyboard: Keyboard::from_mask(mask),
+            ..Default::default()
+        })
+    }
+}
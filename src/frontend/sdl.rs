@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Result};
+use sdl2::{
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+    pixels,
+    rect::Rect,
+    render::WindowCanvas,
+    AudioSubsystem, EventPump,
+};
+
+use crate::{
+    input::keypad::Keypad,
+    models::{
+        audio::{AudioPattern, Synth},
+        errors::{ChipErrors, ConfigError},
+        framebuffer::{Framebuffer, SCHIP_HEIGHT, SCHIP_WIDTH},
+    },
+    palette::{Palette, Rgb},
+};
+
+use super::{
+    bindings::{Action, Bindings, HostKey},
+    AudioSink, Command, DisplaySink, InputSource, InputState,
+};
+
+const SCALE_FACTOR: u32 = 10;
+const SCREEN_WIDTH: u32 = (SCHIP_WIDTH as u32) * SCALE_FACTOR;
+const SCREEN_HEIGHT: u32 = (SCHIP_HEIGHT as u32) * SCALE_FACTOR;
+
+const AUDIO_FREQUENCY: i32 = 44_100;
+
+/// Opens the SDL window, audio device and event pump.
+pub fn init(
+    pitch: f32,
+    volume: f32,
+    muted: bool,
+    bindings: &Bindings,
+) -> Result<(SdlDisplay, SdlAudio, SdlInput)> {
+    let bindings = resolve_bindings(bindings)?;
+
+    let sdl_context = sdl2::init().map_err(|err| anyhow!(err))?;
+    let video_subsystem = sdl_context.video().map_err(|err| anyhow!(err))?;
+    let audio_subsystem = sdl_context.audio().map_err(|err| anyhow!(err))?;
+    let window = video_subsystem
+        .window("Chip8", SCREEN_WIDTH, SCREEN_HEIGHT)
+        .position_centered()
+        .opengl()
+        .build()?;
+
+    let mut canvas = window.into_canvas().build()?;
+    canvas.set_draw_color(pixels::Color::BLACK);
+    canvas.clear();
+    canvas.present();
+
+    let audio = SdlAudio::new(&audio_subsystem, pitch, volume, muted)?;
+    let events = sdl_context.event_pump().map_err(|err| anyhow!(err))?;
+
+    let input = SdlInput {
+        events,
+        bindings,
+        keypad: Keypad::new(),
+        rewind: false,
+        fast_forward: false,
+    };
+
+    let display = SdlDisplay {
+        canvas,
+        palette: Palette::default(),
+    };
+    Ok((display, audio, input))
+}
+
+pub struct SdlDisplay {
+    canvas: WindowCanvas,
+    palette: Palette,
+}
+
+impl DisplaySink for SdlDisplay {
+    fn present(&mut self, gfx: &Framebuffer) -> Result<()> {
+        let colours: Vec<Rgb> = gfx
+            .pixels()
+            .iter()
+            .map(|&pixel| self.palette.colours[pixel as usize])
+            .collect();
+        self.present_blended(gfx, &colours)
+    }
+
+    fn present_blended(&mut self, gfx: &Framebuffer, colours: &[Rgb]) -> Result<()> {
+        let scale = SCREEN_WIDTH / gfx.width() as u32;
+        for (y, row) in colours.chunks(gfx.width()).enumerate() {
+            for (x, &[r, g, b]) in row.iter().enumerate() {
+                let x = (x as u32) * scale;
+                let y = (y as u32) * scale;
+
+                self.canvas.set_draw_color(pixels::Color::RGB(r, g, b));
+                self.canvas
+                    .fill_rect(Rect::new(x as i32, y as i32, scale, scale))
+                    .map_err(|err| anyhow!(err))?;
+            }
+        }
+        self.canvas.present();
+
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) {
+        self.palette = palette.clone();
+    }
+
+    fn show_crash(&mut self, err: &ChipErrors) -> Result<()> {
+        self.canvas
+            .window_mut()
+            .set_title(&format!("Chip8 - crashed: {err}"))?;
+        Ok(())
+    }
+
+    fn show_message(&mut self, message: &str) {
+        // Titles cannot hold NUL bytes, the only way this fails.
+        let _ = self
+            .canvas
+            .window_mut()
+            .set_title(&format!("Chip8 - {message}"));
+    }
+}
+
+/// Square-wave beep, replaced by the XO-CHIP sample loop once a ROM loads one.
+struct Tone {
+    synth: Synth,
+    volume: f32,
+    pattern: Option<AudioPattern>,
+}
+
+impl AudioCallback for Tone {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            let high = self.synth.next_sample(self.pattern.as_ref());
+            *x = if high { self.volume } else { -self.volume };
+        }
+    }
+}
+
+pub struct SdlAudio {
+    device: AudioDevice<Tone>,
+    muted: bool,
+    playing: bool,
+    pattern: Option<AudioPattern>,
+}
+
+impl SdlAudio {
+    fn new(audio: &AudioSubsystem, pitch: f32, volume: f32, muted: bool) -> Result<Self> {
+        let desired_spec = AudioSpecDesired {
+            freq: Some(AUDIO_FREQUENCY),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let device = audio
+            .open_playback(None, &desired_spec, |spec| Tone {
+                synth: Synth::new(spec.freq as u32, pitch),
+                volume,
+                pattern: None,
+            })
+            .map_err(|err| anyhow!(err))?;
+
+        Ok(Self {
+            device,
+            muted,
+            playing: false,
+            pattern: None,
+        })
+    }
+}
+
+impl AudioSink for SdlAudio {
+    fn update(&mut self, sound_active: bool, pattern: Option<AudioPattern>) {
+        if pattern != self.pattern {
+            self.device.lock().pattern = pattern;
+            self.pattern = pattern;
+        }
+
+        let play = sound_active && !self.muted;
+        if play == self.playing {
+            return;
+        }
+
+        if play {
+            self.device.resume();
+        } else {
+            self.device.pause();
+        }
+        self.playing = play;
+    }
+
+    fn toggle_mute(&mut self) -> Option<bool> {
+        self.muted = !self.muted;
+        Some(self.muted)
+    }
+}
+
+pub struct SdlInput {
+    events: EventPump,
+    bindings: Vec<SdlBinding>,
+    keypad: Keypad,
+    rewind: bool,
+    fast_forward: bool,
+}
+
+struct SdlBinding {
+    keycode: Keycode,
+    key: HostKey,
+    action: Action,
+}
+
+impl SdlBinding {
+    /// Commands need exactly the bound modifiers, so F1 and Shift+F1 can do
+    /// different things. Held actions ignore extra modifiers.
+    fn matches(&self, keycode: Keycode, keymod: Mod) -> bool {
+        let modifiers = [
+            (self.key.shift, Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            (self.key.ctrl, Mod::LCTRLMOD | Mod::RCTRLMOD),
+            (self.key.alt, Mod::LALTMOD | Mod::RALTMOD),
+        ];
+        let exact = matches!(self.action, Action::Command(_));
+        self.keycode == keycode
+            && modifiers.iter().all(|&(required, flags)| {
+                let held = keymod.intersects(flags);
+                held == required || (held && !exact)
+            })
+    }
+}
+
+impl InputSource for SdlInput {
+    fn poll(&mut self) -> Result<InputState> {
+        let mut commands = Vec::new();
+        for event in self.events.poll_iter() {
+            match event {
+                Event::Quit { .. } => commands.push(Command::Quit),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } => {
+                    let bound = self
+                        .bindings
+                        .iter()
+                        .filter(|binding| binding.matches(keycode, keymod));
+                    for binding in bound {
+                        match binding.action {
+                            Action::Key(key) => self.keypad.key_down(key),
+                            Action::Command(command) => commands.push(command),
+                            Action::Rewind => self.rewind = true,
+                            Action::FastForward => self.fast_forward = true,
+                        }
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    let bound = self
+                        .bindings
+                        .iter()
+                        .filter(|binding| binding.keycode == keycode);
+                    for binding in bound {
+                        match binding.action {
+                            Action::Key(key) => self.keypad.key_up(key),
+                            Action::Command(_) => {}
+                            Action::Rewind => self.rewind = false,
+                            Action::FastForward => self.fast_forward = false,
+                        }
+                    }
+                }
+                // Key up events are not delivered to unfocused windows.
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => {
+                    self.keypad.release_all();
+                    self.rewind = false;
+                    self.fast_forward = false;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(InputState {
+            keyboard: self.keypad.take_frame(),
+            commands,
+            rewind: self.rewind,
+            fast_forward: self.fast_forward,
+        })
+    }
+}
+
+/// Looks up the SDL keycode of every bound host key.
+fn resolve_bindings(bindings: &Bindings) -> Result<Vec<SdlBinding>, ConfigError> {
+    bindings
+        .iter()
+        .map(|(key, action)| {
+            let keycode = Keycode::from_name(&key.name)
+                .ok_or_else(|| ConfigError::UnknownKeycode(key.name.clone()))?;
+            Ok(SdlBinding {
+                keycode,
+                key: key.clone(),
+                action,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,516 @@
+//! Text-mode frontend for terminals without a display server, e.g. over SSH.
+//!
+//! The screen is drawn with Unicode half-blocks or Braille dots in 24-bit
+//! ANSI colour, rewriting only the cells that changed since the last frame.
+//! Most terminals only report key presses and auto-repeats, so a key counts
+//! as released once it stops repeating. Terminals that support the kitty
+//! keyboard protocol report real releases instead.
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, terminal,
+};
+
+use crate::{
+    input::keypad::Keypad,
+    models::{errors::ChipErrors, framebuffer::Framebuffer},
+    palette::{Palette, Rgb},
+};
+
+use super::{
+    bindings::{Action, Bindings, HostKey},
+    Command, DisplaySink, InputSource, InputState,
+};
+
+/// How long a key stays down after its first press when the terminal does
+/// not report releases. Covers the usual delay before auto-repeat starts.
+pub const DEFAULT_KEY_HOLD: Duration = Duration::from_millis(500);
+/// How long a key stays down after each auto-repeat.
+const REPEAT_HOLD: Duration = Duration::from_millis(100);
+
+const BRAILLE_BASE: u32 = 0x2800;
+/// Braille dot bit for each pixel of a 2x4 cell, indexed by `[y][x]`.
+const BRAILLE_DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// How screen pixels map to character cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// `▀` with separate colours for the top and bottom pixel: 1x2 per cell.
+    #[default]
+    HalfBlocks,
+    /// Braille dots in one colour: 2x4 pixels per cell, for small terminals.
+    Braille,
+}
+
+impl FromStr for Glyphs {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "half" | "halfblock" | "half-blocks" => Ok(Self::HalfBlocks),
+            "braille" => Ok(Self::Braille),
+            _ => anyhow::bail!("Unknown glyph mode {name}, expected half or braille"),
+        }
+    }
+}
+
+/// Puts the terminal in raw mode on the alternate screen and returns the
+/// display and input halves. The terminal is restored when the display drops.
+pub fn init(
+    glyphs: Glyphs,
+    bindings: &Bindings,
+    key_hold: Duration,
+) -> Result<(TerminalDisplay<io::Stdout>, TerminalInput)> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if releases {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
+
+    let mut display = TerminalDisplay::new(stdout, glyphs);
+    display.guard = Some(RawMode {
+        pop_keyboard_flags: releases,
+    });
+    let input = TerminalInput::new(bindings, releases, key_hold);
+    Ok((display, input))
+}
+
+/// One character cell: a glyph with its foreground and background colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    glyph: char,
+    fg: Rgb,
+    bg: Rgb,
+}
+
+/// Draws the screen into a terminal, sending only the cells that changed.
+pub struct TerminalDisplay<W: Write> {
+    out: W,
+    glyphs: Glyphs,
+    palette: Palette,
+    columns: usize,
+    cells: Vec<Cell>,
+    /// Status line shown under the screen.
+    status: String,
+    /// Restores the terminal when the display drops, if set up by `init`.
+    guard: Option<RawMode>,
+}
+
+impl<W: Write> TerminalDisplay<W> {
+    pub fn new(out: W, glyphs: Glyphs) -> Self {
+        Self {
+            out,
+            glyphs,
+            palette: Palette::default(),
+            columns: 0,
+            cells: Vec::new(),
+            status: String::new(),
+            guard: None,
+        }
+    }
+
+    fn cell_size(&self) -> (usize, usize) {
+        match self.glyphs {
+            Glyphs::HalfBlocks => (1, 2),
+            Glyphs::Braille => (2, 4),
+        }
+    }
+
+    /// The palette colour of every pixel of `gfx`.
+    fn colours(&self, gfx: &Framebuffer) -> Vec<Rgb> {
+        gfx.pixels()
+            .iter()
+            .map(|&pixel| self.palette.colours[pixel as usize])
+            .collect()
+    }
+
+    /// Writes the status line into the row under the screen.
+    fn draw_status(&self, buffer: &mut Vec<u8>) {
+        let row = self.cells.len() / self.columns.max(1) + 1;
+        buffer.extend(format!("\x1b[{row};1H\x1b[0m\x1b[2K{}", self.status).bytes());
+    }
+
+    fn render(&self, gfx: &Framebuffer, colours: &[Rgb]) -> Vec<Cell> {
+        let (cell_width, cell_height) = self.cell_size();
+        let columns = gfx.width().div_ceil(cell_width);
+        let rows = gfx.height().div_ceil(cell_height);
+        let background = self.palette.colours[0];
+        let pixel = |x: usize, y: usize| {
+            if x < gfx.width() && y < gfx.height() {
+                colours[y * gfx.width() + x]
+            } else {
+                background
+            }
+        };
+
+        let mut cells = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * cell_width, row * cell_height);
+                cells.push(match self.glyphs {
+                    Glyphs::HalfBlocks => half_block(pixel(x, y), pixel(x, y + 1)),
+                    Glyphs::Braille => {
+                        let mut dots = 0;
+                        let mut counts: Vec<(Rgb, usize)> = Vec::new();
+                        for (dy, bits) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, bit) in bits.iter().enumerate() {
+                                let colour = pixel(x + dx, y + dy);
+                                if colour != background {
+                                    dots |= bit;
+                                    match counts.iter_mut().find(|(seen, _)| *seen == colour) {
+                                        Some((_, count)) => *count += 1,
+                                        None => counts.push((colour, 1)),
+                                    }
+                                }
+                            }
+                        }
+                        // A cell has a single colour: use the most common one.
+                        let fg = counts
+                            .iter()
+                            .max_by_key(|(_, count)| *count)
+                            .map_or(self.palette.colours[1], |(colour, _)| *colour);
+                        Cell {
+                            glyph: char::from_u32(BRAILLE_BASE + dots as u32).unwrap_or(' '),
+                            fg,
+                            bg: background,
+                        }
+                    }
+                });
+            }
+        }
+        cells
+    }
+}
+
+impl<W: Write> DisplaySink for TerminalDisplay<W> {
+    fn present(&mut self, gfx: &Framebuffer) -> Result<()> {
+        let colours = self.colours(gfx);
+        self.present_blended(gfx, &colours)
+    }
+
+    fn present_blended(&mut self, gfx: &Framebuffer, colours: &[Rgb]) -> Result<()> {
+        let columns = gfx.width().div_ceil(self.cell_size().0);
+        let cells = self.render(gfx, colours);
+
+        let mut buffer = Vec::new();
+        let cleared = columns != self.columns || cells.len() != self.cells.len();
+        if cleared {
+            // The resolution changed: start over from a blank screen.
+            buffer.extend_from_slice(b"\x1b[0m\x1b[2J");
+            self.columns = columns;
+            self.cells.clear();
+        }
+
+        let mut cursor = None;
+        let mut colours = None;
+        for (index, cell) in cells.iter().enumerate() {
+            if self.cells.get(index) == Some(cell) {
+                continue;
+            }
+
+            let position = (index / columns, index % columns);
+            if cursor != Some(position) {
+                buffer.extend(format!("\x1b[{};{}H", position.0 + 1, position.1 + 1).bytes());
+            }
+            if colours != Some((cell.fg, cell.bg)) {
+                let [fr, fg, fb] = cell.fg;
+                let [br, bg, bb] = cell.bg;
+                buffer.extend(format!("\x1b[38;2;{fr};{fg};{fb};48;2;{br};{bg};{bb}m").bytes());
+                colours = Some((cell.fg, cell.bg));
+            }
+            let mut utf8 = [0; 4];
+            buffer.extend_from_slice(cell.glyph.encode_utf8(&mut utf8).as_bytes());
+            cursor = Some((position.0, position.1 + 1));
+        }
+        self.cells = cells;
+        if cleared && !self.status.is_empty() {
+            self.draw_status(&mut buffer);
+        }
+
+        if !buffer.is_empty() {
+            buffer.extend_from_slice(b"\x1b[0m");
+            self.out.write_all(&buffer)?;
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) {
+        self.palette = palette.clone();
+        // Every cell changes colour, so forget what is on screen.
+        self.cells.clear();
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.status = message.to_string();
+        let mut buffer = Vec::new();
+        self.draw_status(&mut buffer);
+        // A lost status line is not worth stopping the emulator for; the
+        // next `present` reports a broken terminal anyway.
+        let _ = self.out.write_all(&buffer).and_then(|()| self.out.flush());
+    }
+
+    fn show_crash(&mut self, err: &ChipErrors) -> Result<()> {
+        self.show_message(&format!("Crashed: {err}"));
+        Ok(())
+    }
+}
+
+/// Undoes the terminal setup of `init` when dropped.
+struct RawMode {
+    pop_keyboard_flags: bool,
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.pop_keyboard_flags {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Top pixel in the foreground, bottom pixel in the background.
+fn half_block(top: Rgb, bottom: Rgb) -> Cell {
+    if top == bottom {
+        Cell {
+            glyph: ' ',
+            fg: bottom,
+            bg: bottom,
+        }
+    } else {
+        Cell {
+            glyph: '▀',
+            fg: top,
+            bg: bottom,
+        }
+    }
+}
+
+/// Reads keys from raw-mode stdin and maps them through the key bindings.
+pub struct TerminalInput {
+    bindings: Vec<(HostKey, Action)>,
+    keypad: Keypad,
+    /// The terminal reports key releases, so no emulation is needed.
+    releases: bool,
+    key_hold: Duration,
+    /// Held actions, with the time they count as released unless the
+    /// terminal reports releases.
+    held: Vec<(Action, Option<Instant>)>,
+}
+
+impl TerminalInput {
+    fn new(bindings: &Bindings, releases: bool, key_hold: Duration) -> Self {
+        Self {
+            bindings: bindings
+                .iter()
+                .map(|(key, action)| (key.clone(), action))
+                .collect(),
+            keypad: Keypad::new(),
+            releases,
+            key_hold,
+            held: Vec::new(),
+        }
+    }
+
+    fn press(&mut self, action: Action, repeat: bool) {
+        let hold = if repeat { REPEAT_HOLD } else { self.key_hold };
+        let until = (!self.releases).then(|| Instant::now() + hold);
+        match self.held.iter_mut().find(|(held, _)| *held == action) {
+            Some((_, deadline)) => *deadline = (*deadline).max(until),
+            None => self.held.push((action, until)),
+        }
+        if let Action::Key(key) = action {
+            self.keypad.key_down(key);
+        }
+    }
+
+    fn release(&mut self, action: Action) {
+        self.held.retain(|(held, _)| *held != action);
+        if let Action::Key(key) = action {
+            self.keypad.key_up(key);
+        }
+    }
+
+    fn is_held(&self, action: Action) -> bool {
+        self.held.iter().any(|(held, _)| *held == action)
+    }
+
+    fn handle(&mut self, event: KeyEvent, commands: &mut Vec<Command>) {
+        // Raw mode swallows the interrupt signal, so Ctrl+C always quits.
+        if event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL) {
+            commands.push(Command::Quit);
+            return;
+        }
+        let Some(name) = key_name(event.code) else {
+            return;
+        };
+
+        let actions: Vec<Action> = self
+            .bindings
+            .iter()
+            .filter(|(key, action)| {
+                key.name.eq_ignore_ascii_case(&name) && modifiers_match(key, *action, event)
+            })
+            .map(|(_, action)| *action)
+            .collect();
+        for action in actions {
+            match (event.kind, action) {
+                (KeyEventKind::Release, _) => self.release(action),
+                (KeyEventKind::Press, Action::Command(command)) => commands.push(command),
+                (KeyEventKind::Repeat, Action::Command(_)) => {}
+                (kind, _) => self.press(action, kind == KeyEventKind::Repeat),
+            }
+        }
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn poll(&mut self) -> Result<InputState> {
+        let mut commands = Vec::new();
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                self.handle(key, &mut commands);
+            }
+        }
+
+        let now = Instant::now();
+        let expired: Vec<Action> = self
+            .held
+            .iter()
+            .filter(|(_, until)| until.is_some_and(|until| until <= now))
+            .map(|(action, _)| *action)
+            .collect();
+        for action in expired {
+            self.release(action);
+        }
+
+        Ok(InputState {
+            keyboard: self.keypad.take_frame(),
+            commands,
+            rewind: self.is_held(Action::Rewind),
+            fast_forward: self.is_held(Action::FastForward),
+        })
+    }
+}
+
+/// Commands need exactly the bound modifiers; held actions ignore extra ones.
+/// Shift is ignored for characters, which arrive already shifted.
+fn modifiers_match(key: &HostKey, action: Action, event: KeyEvent) -> bool {
+    let shift =
+        event.modifiers.contains(KeyModifiers::SHIFT) && !matches!(event.code, KeyCode::Char(_));
+    let modifiers = [
+        (key.shift, shift),
+        (key.ctrl, event.modifiers.contains(KeyModifiers::CONTROL)),
+        (key.alt, event.modifiers.contains(KeyModifiers::ALT)),
+    ];
+    let exact = matches!(action, Action::Command(_));
+    modifiers
+        .iter()
+        .all(|&(required, held)| held == required || (held && !exact))
+}
+
+/// The SDL name of a terminal key, as used in key bindings.
+fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Char(c) => return Some(c.to_uppercase().collect()),
+        KeyCode::F(n) => return Some(format!("F{n}")),
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Enter => "Return",
+        KeyCode::Tab | KeyCode::BackTab => "Tab",
+        KeyCode::Esc => "Escape",
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Insert => "Insert",
+        KeyCode::Delete => "Delete",
+        KeyCode::Pause => "Pause",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::framebuffer::{CHIP8_HEIGHT, CHIP8_WIDTH};
+
+    fn present(display: &mut TerminalDisplay<Vec<u8>>, gfx: &Framebuffer) -> String {
+        display.out.clear();
+        display.present(gfx).unwrap();
+        String::from_utf8(display.out.clone()).unwrap()
+    }
+
+    #[test]
+    fn only_changed_cells_are_redrawn() {
+        let mut display = TerminalDisplay::new(Vec::new(), Glyphs::HalfBlocks);
+        let mut gfx = Framebuffer::new(CHIP8_WIDTH, CHIP8_HEIGHT);
+        let full = present(&mut display, &gfx);
+        assert_eq!(full.matches(' ').count(), 64 * 16);
+
+        assert!(present(&mut display, &gfx).is_empty());
+
+        gfx.toggle(10, 5, 1);
+        let update = present(&mut display, &gfx);
+        assert!(update.starts_with("\x1b[3;11H"), "{update:?}");
+        assert_eq!(update.chars().filter(|&c| c == '▀' || c == ' ').count(), 1);
+        assert!(update.contains('▀'));
+    }
+
+    #[test]
+    fn status_line_sits_under_the_screen_and_survives_a_clear() {
+        let mut display = TerminalDisplay::new(Vec::new(), Glyphs::HalfBlocks);
+        present(&mut display, &Framebuffer::new(CHIP8_WIDTH, CHIP8_HEIGHT));
+
+        display.out.clear();
+        display.show_message("Paused");
+        let status = String::from_utf8(display.out.clone()).unwrap();
+        assert_eq!(status, "\x1b[17;1H\x1b[0m\x1b[2KPaused");
+
+        let hires = present(&mut display, &Framebuffer::new(128, 64));
+        assert!(
+            hires.contains("\x1b[33;1H\x1b[0m\x1b[2KPaused"),
+            "{hires:?}"
+        );
+    }
+
+    #[test]
+    fn braille_packs_two_by_four_pixels() {
+        let mut display = TerminalDisplay::new(Vec::new(), Glyphs::Braille);
+        let mut gfx = Framebuffer::new(CHIP8_WIDTH, CHIP8_HEIGHT);
+        gfx.toggle(1, 3, 1);
+        gfx.toggle(0, 0, 1);
+        let cells = display.render(&gfx, &display.colours(&gfx));
+        assert_eq!(cells.len(), 32 * 8);
+        assert_eq!(cells[0].glyph, '\u{2881}');
+        assert_eq!(
+            present(&mut display, &gfx).matches('\u{2800}').count(),
+            32 * 8 - 1
+        );
+    }
+}
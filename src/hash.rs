@@ -0,0 +1,57 @@
+use crate::models::framebuffer::Framebuffer;
+
+/// 64-bit FNV-1a, used to identify ROMs and fingerprint machine states.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// Fingerprint of a screen, covering its resolution and every plane bit.
+pub fn framebuffer_hash(gfx: &Framebuffer) -> u64 {
+    let mut data = Vec::with_capacity(4 + gfx.pixels().len());
+    data.extend_from_slice(&(gfx.width() as u16).to_le_bytes());
+    data.extend_from_slice(&(gfx.height() as u16).to_le_bytes());
+    data.extend_from_slice(gfx.pixels());
+    fnv1a(&data)
+}
+
+/// Remainders of every byte value, so CRC-32 runs a byte per step instead of
+/// a bit. Save states cover all of memory and rewind takes one every frame.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3).
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_standard_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,104 @@
+//! Still image export of the screen, without any image library.
+
+use crate::{hash::crc32, models::framebuffer::Framebuffer, palette::Palette};
+
+/// Largest payload of a stored (uncompressed) deflate block.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// Encodes the screen as an indexed-colour PNG, each pixel `scale` times
+/// larger, in the colours of `palette`. The image data is stored uncompressed.
+pub fn encode_png(gfx: &Framebuffer, scale: usize, palette: &Palette) -> Vec<u8> {
+    let scale = scale.max(1);
+    let width = gfx.width() * scale;
+    let height = gfx.height() * scale;
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8-bit palette indices, default compression, filtering and no interlace.
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    put_chunk(&mut png, b"IHDR", &header);
+    put_chunk(&mut png, b"PLTE", palette.colours.as_flattened());
+
+    let mut scanlines = Vec::with_capacity((width + 1) * height);
+    for row in gfx.rows() {
+        // Each scanline starts with its filter type, 0 for none.
+        let mut scanline = vec![0];
+        scanline.extend(
+            row.iter()
+                .flat_map(|&pixel| std::iter::repeat_n(pixel, scale)),
+        );
+        for _ in 0..scale {
+            scanlines.extend_from_slice(&scanline);
+        }
+    }
+    put_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    put_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+/// Encodes the screen as a binary PBM, lit pixels (on any plane) in black.
+pub fn encode_pbm(gfx: &Framebuffer, scale: usize) -> Vec<u8> {
+    let scale = scale.max(1);
+    let width = gfx.width() * scale;
+    let height = gfx.height() * scale;
+
+    let mut pbm = format!("P4\n{width} {height}\n").into_bytes();
+    for row in gfx.rows() {
+        let bits: Vec<bool> = row
+            .iter()
+            .flat_map(|&pixel| std::iter::repeat_n(pixel != 0, scale))
+            .collect();
+        let packed: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, &bit)| acc | (bit as u8) << (7 - i))
+            })
+            .collect();
+        for _ in 0..scale {
+            pbm.extend_from_slice(&packed);
+        }
+    }
+
+    pbm
+}
+
+fn put_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream made of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        out.push(last as u8);
+        out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
@@ -1,51 +1,72 @@
-use std::collections::HashMap;
-
-use sdl2::keyboard::Keycode;
-
-use crate::models::errors::ChipErrors;
-
-#[derive(Debug)]
+/// State of the 16-key hex keypad, bit N set meaning key N is held down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Keyboard {
-    key_status: HashMap<Key, bool>,
+    mask: u16,
+    /// Keys that went down since the previous frame, see `Keypad`.
+    pressed: u16,
+    /// Keys that went up since the previous frame, see `Keypad`.
+    released: u16,
 }
 
 impl Keyboard {
     pub fn new() -> Self {
-        let mut key_status = HashMap::new();
+        Self::default()
+    }
 
-        key_status.insert(Key::Key1, false);
-        key_status.insert(Key::Key2, false);
-        key_status.insert(Key::Key3, false);
-        key_status.insert(Key::KeyC, false);
-        key_status.insert(Key::Key4, false);
-        key_status.insert(Key::Key5, false);
-        key_status.insert(Key::Key6, false);
-        key_status.insert(Key::KeyD, false);
-        key_status.insert(Key::Key7, false);
-        key_status.insert(Key::Key8, false);
-        key_status.insert(Key::Key9, false);
-        key_status.insert(Key::KeyE, false);
-        key_status.insert(Key::KeyA, false);
-        key_status.insert(Key::Key0, false);
-        key_status.insert(Key::KeyB, false);
-        key_status.insert(Key::KeyF, false);
+    /// Builds a keypad state from a bitmask, bit N set meaning key N is down.
+    pub fn from_mask(mask: u16) -> Self {
+        Self {
+            mask,
+            ..Self::default()
+        }
+    }
+
+    /// Like `from_mask`, with the keys that went down and up since the
+    /// previous frame.
+    pub(crate) fn with_edges(mask: u16, pressed: u16, released: u16) -> Self {
+        Self {
+            mask,
+            pressed,
+            released,
+        }
+    }
 
-        Self { key_status }
+    /// The pressed keys as a bitmask, bit N set meaning key N is down.
+    pub fn mask(&self) -> u16 {
+        self.mask
     }
 
     pub fn press(&mut self, key: Key) {
-        *self.key_status.entry(key).or_default() = true;
+        self.mask |= 1 << key.get_code();
     }
 
-    pub fn get_pressed_key(&self) -> Option<u8> {
-        self.key_status
-            .iter()
-            .find(|(_, value)| **value)
-            .map(|(key, _)| key.get_code())
+    pub fn release(&mut self, key: Key) {
+        self.mask &= !(1 << key.get_code());
+    }
+
+    /// Whether the key with hex code `code` is held. Only the low nibble is
+    /// used, as on the COSMAC VIP.
+    pub fn is_pressed(&self, code: u8) -> bool {
+        self.mask & 1 << (code & 0xF) != 0
+    }
+
+    /// Whether the key with hex code `code` went down since the previous frame.
+    pub fn just_pressed(&self, code: u8) -> bool {
+        self.pressed & 1 << (code & 0xF) != 0
+    }
+
+    /// Whether the key with hex code `code` went up since the previous frame.
+    pub fn just_released(&self, code: u8) -> bool {
+        self.released & 1 << (code & 0xF) != 0
+    }
+
+    /// The lowest-numbered key being held, if any.
+    pub fn first_pressed(&self) -> Option<u8> {
+        (self.mask != 0).then(|| self.mask.trailing_zeros() as u8)
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Key {
     Key1,
     Key2,
@@ -66,28 +87,27 @@ pub enum Key {
 }
 
 impl Key {
-    pub fn parse(code: sdl2::keyboard::Keycode) -> Result<Key, ChipErrors> {
+    pub fn from_code(code: u8) -> Option<Key> {
         let key = match code {
-            Keycode::Num1 => Key::Key1,
-            Keycode::Num2 => Key::Key2,
-            Keycode::Num3 => Key::Key3,
-            Keycode::Num4 => Key::KeyC,
-            Keycode::Q => Key::Key4,
-            Keycode::W => Key::Key5,
-            Keycode::E => Key::Key6,
-            Keycode::R => Key::KeyD,
-            Keycode::A => Key::Key7,
-            Keycode::S => Key::Key8,
-            Keycode::D => Key::Key9,
-            Keycode::F => Key::KeyE,
-            Keycode::Z => Key::KeyA,
-            Keycode::X => Key::Key0,
-            Keycode::C => Key::KeyB,
-            Keycode::V => Key::KeyF,
-            _ => return Err(ChipErrors::UnknownKeycode(code)),
+            0x0 => Key::Key0,
+            0x1 => Key::Key1,
+            0x2 => Key::Key2,
+            0x3 => Key::Key3,
+            0x4 => Key::Key4,
+            0x5 => Key::Key5,
+            0x6 => Key::Key6,
+            0x7 => Key::Key7,
+            0x8 => Key::Key8,
+            0x9 => Key::Key9,
+            0xa => Key::KeyA,
+            0xb => Key::KeyB,
+            0xc => Key::KeyC,
+            0xd => Key::KeyD,
+            0xe => Key::KeyE,
+            0xf => Key::KeyF,
+            _ => return None,
         };
-
-        Ok(key)
+        Some(key)
     }
 
     pub fn get_code(&self) -> u8 {
@@ -0,0 +1,110 @@
+use super::keyboard::{Key, Keyboard};
+
+/// Keypad driven by key down and key up events instead of polled snapshots.
+///
+/// A key that goes down and back up between two frames is latched, so the
+/// next frame still sees it held and short taps are never lost. Each frame
+/// also reports the keys that went down or up since the one before.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keypad {
+    held: u16,
+    latched: u16,
+    pressed: u16,
+    released: u16,
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn key_down(&mut self, key: Key) {
+        let bit = 1 << key.get_code();
+        if self.held & bit == 0 {
+            self.pressed |= bit;
+        }
+        self.held |= bit;
+        self.latched |= bit;
+    }
+
+    pub fn key_up(&mut self, key: Key) {
+        let bit = 1 << key.get_code();
+        if self.held & bit != 0 {
+            self.released |= bit;
+        }
+        self.held &= !bit;
+    }
+
+    /// Lets go of every key, e.g. when the window loses focus.
+    pub fn release_all(&mut self) {
+        self.released |= self.held;
+        self.held = 0;
+    }
+
+    /// Whether `key` is down right now, ignoring latched taps.
+    pub fn is_held(&self, key: Key) -> bool {
+        self.held & 1 << key.get_code() != 0
+    }
+
+    /// The keypad for the next frame: keys held now or tapped since the last
+    /// call, along with their edges. Clears the latches and edges.
+    pub fn take_frame(&mut self) -> Keyboard {
+        let keyboard = Keyboard::with_edges(self.held | self.latched, self.pressed, self.released);
+        self.latched = 0;
+        self.pressed = 0;
+        self.released = 0;
+        keyboard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tap_between_frames_is_latched() {
+        let mut keypad = Keypad::new();
+        keypad.key_down(Key::Key5);
+        keypad.key_up(Key::Key5);
+        assert!(!keypad.is_held(Key::Key5));
+
+        let frame = keypad.take_frame();
+        assert!(frame.is_pressed(5));
+        assert!(frame.just_pressed(5));
+        assert!(frame.just_released(5));
+
+        let frame = keypad.take_frame();
+        assert!(!frame.is_pressed(5));
+        assert!(!frame.just_released(5));
+    }
+
+    #[test]
+    fn held_key_has_a_single_press_edge() {
+        let mut keypad = Keypad::new();
+        keypad.key_down(Key::KeyA);
+        keypad.key_down(Key::KeyA);
+        assert!(keypad.take_frame().just_pressed(0xA));
+
+        let frame = keypad.take_frame();
+        assert!(frame.is_pressed(0xA));
+        assert!(!frame.just_pressed(0xA));
+
+        keypad.key_up(Key::KeyA);
+        let frame = keypad.take_frame();
+        assert!(!frame.is_pressed(0xA));
+        assert!(frame.just_released(0xA));
+    }
+
+    #[test]
+    fn release_all_reports_release_edges() {
+        let mut keypad = Keypad::new();
+        keypad.key_down(Key::Key1);
+        keypad.key_down(Key::KeyF);
+        keypad.take_frame();
+
+        keypad.release_all();
+        let frame = keypad.take_frame();
+        assert_eq!(frame.mask(), 0);
+        assert!(frame.just_released(1) && frame.just_released(0xF));
+    }
+}
@@ -1,3 +1,15 @@
+pub mod asm;
+mod bytes;
+pub mod debugger;
+pub mod disasm;
 mod font;
+pub mod frontend;
+pub mod hash;
+pub mod image;
 pub mod input;
 pub mod models;
+pub mod movie;
+pub mod palette;
+pub mod rewind;
+pub mod video;
+pub mod wav;
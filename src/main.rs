@@ -1,95 +1,216 @@
-use std::time::Duration;
+use std::{fs::File, io::BufWriter, path::PathBuf};
 
-use anyhow::{anyhow, Result};
+use anyhow::{bail, Context, Result};
 use chip_8::{
     self,
-    input::keyboard::{Key, Keyboard},
-    models::chip8::{Chip8, CHIP8_HEIGHT, CHIP8_WIDTH},
+    debugger::Debugger,
+    frontend::{
+        bindings::Config,
+        options::{rom_path, CommonOptions},
+        sdl, Emulator, DEFAULT_VIDEO_SCALE,
+    },
+    hash::fnv1a,
+    models::{audio::DEFAULT_BEEP_FREQUENCY, chip8::Chip8},
+    movie::{Movie, Playback},
+    rewind::Rewind,
+    video::{GifEncoder, Y4mEncoder},
+    wav::{WavWriter, WAV_SAMPLE_RATE},
 };
-use sdl2::{event::Event, keyboard::Keycode, pixels, rect::Rect, EventPump};
 
-const SCALE_FACTOR: u32 = 20;
-const SCREEN_WIDTH: u32 = (CHIP8_WIDTH as u32) * SCALE_FACTOR;
-const SCREEN_HEIGHT: u32 = (CHIP8_HEIGHT as u32) * SCALE_FACTOR;
-
-fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    let filename = &args[1];
-    let rom = std::fs::read(filename)?;
-    let mut chip = Chip8::new(rom);
-
-    let sdl_context = sdl2::init().map_err(|err| anyhow!(err))?;
-    let video_subsystem = sdl_context.video().map_err(|err| anyhow!(err))?;
-    let window = video_subsystem
-        .window("Chip8", SCREEN_WIDTH, SCREEN_HEIGHT)
-        .position_centered()
-        .opengl()
-        .build()?;
-
-    let mut canvas = window.into_canvas().build()?;
-
-    canvas.set_draw_color(pixels::Color::BLACK);
-    canvas.clear();
-    canvas.present();
-
-    let sleep_duration = Duration::from_millis(2);
-    let mut events = sdl_context.event_pump().map_err(|err| anyhow!(err))?;
-
-    loop {
-        let keyboard = poll(&mut events)?;
-
-        let result = chip.emulateCycle(&keyboard)?;
-        if result.draw_update {
-            for (y, row) in result.gfx.iter().enumerate() {
-                for (x, &pixel) in row.iter().enumerate() {
-                    let x = (x as u32) * SCALE_FACTOR;
-                    let y = (y as u32) * SCALE_FACTOR;
+const DEFAULT_VOLUME: f32 = 0.25;
+
+const USAGE: &str = "Usage: chip-8 [--pitch HZ] [--volume V] [--mute] [--ipf N | --hz N] \
+                     [--quirks PRESET] [--quirk NAME=VALUE]... [--rewind-mb MB] [--seed N] \
+                     [--record FILE | --play FILE] [--config FILE] \
+                     [--gif FILE] [--y4m FILE] [--video-scale N] [--wav FILE] \
+                     [--palette NAME|COLOURS] [--persistence MODE] [--debug] ROM";
+
+struct Options {
+    filename: String,
+    pitch: f32,
+    volume: f32,
+    muted: bool,
+    common: CommonOptions,
+    debug: bool,
+    record: Option<String>,
+    play: Option<String>,
+    config: Option<PathBuf>,
+    gif: Option<String>,
+    y4m: Option<String>,
+    video_scale: usize,
+    wav: Option<String>,
+}
 
-                    canvas.set_draw_color(color(pixel));
-                    canvas
-                        .fill_rect(Rect::new(x as i32, y as i32, SCALE_FACTOR, SCALE_FACTOR))
-                        .map_err(|err| anyhow!(err))?;
+impl Options {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut filename = None;
+        let mut pitch = DEFAULT_BEEP_FREQUENCY;
+        let mut volume = DEFAULT_VOLUME;
+        let mut muted = false;
+        let mut common = CommonOptions::default();
+        let mut debug = false;
+        let mut record = None;
+        let mut play = None;
+        let mut config = None;
+        let mut gif = None;
+        let mut y4m = None;
+        let mut video_scale = DEFAULT_VIDEO_SCALE;
+        let mut wav = None;
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--pitch" => {
+                    let value = args.next().context("--pitch expects a frequency in Hz")?;
+                    pitch = value.parse().context("Invalid --pitch value")?;
+                }
+                "--volume" => {
+                    let value = args
+                        .next()
+                        .context("--volume expects a value in 0.0..=1.0")?;
+                    volume = value.parse().context("Invalid --volume value")?;
+                }
+                "--mute" => muted = true,
+                "--debug" => debug = true,
+                "--record" => {
+                    record = Some(
+                        args.next()
+                            .context("--record expects a movie file")?
+                            .clone(),
+                    );
+                }
+                "--play" => {
+                    play = Some(args.next().context("--play expects a movie file")?.clone());
+                }
+                "--config" => {
+                    let value = args.next().context("--config expects a TOML file")?;
+                    config = Some(PathBuf::from(value));
+                }
+                "--gif" => {
+                    gif = Some(args.next().context("--gif expects a file name")?.clone());
+                }
+                "--y4m" => {
+                    y4m = Some(args.next().context("--y4m expects a file name")?.clone());
+                }
+                "--video-scale" => {
+                    let value = args.next().context("--video-scale expects a pixel size")?;
+                    video_scale = value.parse().context("Invalid --video-scale value")?;
+                }
+                "--wav" => {
+                    wav = Some(args.next().context("--wav expects a file name")?.clone());
                 }
+                _ if common.parse_flag(arg, &mut args)? => {}
+                _ => filename = Some(rom_path(arg, USAGE)),
             }
-            canvas.present();
         }
 
-        std::thread::sleep(sleep_duration);
+        if debug && (record.is_some() || play.is_some()) {
+            bail!("--debug cannot be combined with --record or --play");
+        }
+
+        Ok(Self {
+            filename: filename.context(USAGE)?,
+            pitch,
+            volume: volume.clamp(0.0, 1.0),
+            muted,
+            common: common.finish()?,
+            debug,
+            record,
+            play,
+            config,
+            gif,
+            y4m,
+            video_scale,
+            wav,
+        })
     }
 }
 
-fn poll(events: &mut EventPump) -> Result<Keyboard> {
-    if events.poll_iter().any(|event| {
-        if let Event::Quit { .. } = event {
-            true
-        } else {
-            false
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let options = Options::parse(&args)?;
+    let rom = std::fs::read(&options.filename)?;
+
+    // A movie dictates everything that affects emulation.
+    let playback = match &options.play {
+        Some(path) => {
+            let data = std::fs::read(path).with_context(|| format!("Cannot read {path}"))?;
+            Some(Playback::new(Movie::from_bytes(&data)?))
         }
-    }) {
-        return Err(anyhow!("User closed application. Shutting down"));
-    }
-
-    let keys: Vec<Keycode> = events
-        .keyboard_state()
-        .pressed_scancodes()
-        .filter_map(Keycode::from_scancode)
-        .collect();
-
-    let mut keyboard = Keyboard::new();
-
-    for key in keys {
-        if let Ok(keyboard_key) = Key::parse(key) {
-            keyboard.press(keyboard_key);
+        None => None,
+    };
+
+    let (chip, cycles_per_frame) = match &playback {
+        Some(playback) => (
+            playback.movie().start(rom.clone())?,
+            playback.movie().cycles_per_frame,
+        ),
+        None => {
+            let mut chip = Chip8::new(rom.clone(), options.common.quirks)?;
+            if let Some(seed) = options.common.seed {
+                chip = chip.with_seed(seed);
+            }
+            (chip, options.common.cycles_per_frame)
         }
+    };
+    let recording = options
+        .record
+        .as_ref()
+        .map(|_| Movie::new(&rom, &chip, cycles_per_frame));
+
+    let config = Config::load(options.config.as_deref(), fnv1a(&rom))?;
+    let (display, audio, input) = sdl::init(
+        options.pitch,
+        options.volume,
+        options.muted,
+        &config.bindings,
+    )?;
+    let palette = options
+        .common
+        .palette
+        .or(config.palette)
+        .unwrap_or_default();
+    let mut emulator = Emulator::new(chip, display, audio, input, cycles_per_frame)
+        .with_save_slots(&options.filename)
+        .with_video_scale(options.video_scale)
+        .with_palette(palette)
+        .with_persistence(options.common.persistence);
+    if let Some(path) = &options.gif {
+        let file = File::create(path).with_context(|| format!("Cannot create {path}"))?;
+        let encoder = GifEncoder::new(BufWriter::new(file), options.video_scale);
+        emulator = emulator.with_video(path, Box::new(encoder));
+    }
+    if let Some(path) = &options.y4m {
+        let file = File::create(path).with_context(|| format!("Cannot create {path}"))?;
+        let encoder = Y4mEncoder::new(BufWriter::new(file), options.video_scale);
+        emulator = emulator.with_video(path, Box::new(encoder));
+    }
+    if let Some(path) = &options.wav {
+        let file = File::create(path).with_context(|| format!("Cannot create {path}"))?;
+        let writer = WavWriter::new(BufWriter::new(file), WAV_SAMPLE_RATE, options.pitch)
+            .with_context(|| format!("Cannot write {path}"))?;
+        emulator = emulator.with_wav(path, writer);
+    }
+    if options.common.rewind_mb > 0 {
+        emulator = emulator.with_rewind(Rewind::new(options.common.rewind_mb << 20));
+    }
+    if options.debug {
+        emulator = emulator.with_debugger(Debugger::new().with_console());
+    }
+    if let Some(playback) = playback {
+        emulator = emulator.with_playback(playback);
+    }
+    if let Some(movie) = recording {
+        emulator = emulator.with_recording(movie);
     }
 
-    Ok(keyboard)
-}
+    emulator.run()?;
+    emulator.finish_recordings()?;
 
-fn color(value: u8) -> pixels::Color {
-    if value == 0 {
-        pixels::Color::BLUE
-    } else {
-        pixels::Color::GREEN
+    if let (Some(path), Some(movie)) = (&options.record, emulator.take_recording()) {
+        std::fs::write(path, movie.to_bytes()).with_context(|| format!("Cannot write {path}"))?;
+        println!("Recorded {} frames to {path}", movie.frames.len());
     }
+
+    Ok(())
 }
@@ -0,0 +1,75 @@
+/// Default XO-CHIP pitch register value, which plays the pattern at 4000 bits/s.
+pub const DEFAULT_PITCH: u8 = 64;
+pub const PATTERN_BYTES: usize = 16;
+pub const PATTERN_BITS: usize = PATTERN_BYTES * 8;
+
+/// XO-CHIP 1-bit audio: a 128-bit sample loop played back at a programmable rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioPattern {
+    pub buffer: [u8; PATTERN_BYTES],
+    pub pitch: u8,
+}
+
+impl AudioPattern {
+    /// Playback rate in bits per second, `4000 * 2^((pitch - 64) / 48)`.
+    pub fn playback_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    /// Returns whether bit `index` (wrapping around the 128-bit loop) is set.
+    pub fn bit(&self, index: usize) -> bool {
+        let index = index % PATTERN_BITS;
+        self.buffer[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+}
+
+impl Default for AudioPattern {
+    fn default() -> Self {
+        Self {
+            buffer: [0; PATTERN_BYTES],
+            pitch: DEFAULT_PITCH,
+        }
+    }
+}
+
+/// Frequency of the square-wave beep played while no XO-CHIP pattern is loaded.
+pub const DEFAULT_BEEP_FREQUENCY: f32 = 440.0;
+
+/// 1-bit oscillator for the sound timer output. It only advances while
+/// sampled, so the waveform resumes where it left off after a silence.
+#[derive(Debug, Clone)]
+pub struct Synth {
+    sample_rate: f32,
+    phase_inc: f32,
+    phase: f32,
+    pattern_position: f32,
+}
+
+impl Synth {
+    pub fn new(sample_rate: u32, beep_frequency: f32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            phase_inc: beep_frequency / sample_rate as f32,
+            phase: 0.0,
+            pattern_position: 0.0,
+        }
+    }
+
+    /// The next output level: the beep, or `pattern` if one is loaded.
+    pub fn next_sample(&mut self, pattern: Option<&AudioPattern>) -> bool {
+        match pattern {
+            Some(pattern) => {
+                let high = pattern.bit(self.pattern_position as usize);
+                self.pattern_position = (self.pattern_position
+                    + pattern.playback_rate() / self.sample_rate)
+                    % PATTERN_BITS as f32;
+                high
+            }
+            None => {
+                let high = self.phase <= 0.5;
+                self.phase = (self.phase + self.phase_inc) % 1.0;
+                high
+            }
+        }
+    }
+}
@@ -1,47 +1,101 @@
-use rand::{prelude::ThreadRng, Rng};
+use std::ops::Range;
 
-use crate::{font::FONT, input::keyboard::Keyboard};
+use crate::{
+    font::{BIG_FONT, FONT},
+    input::keyboard::Keyboard,
+};
 
-use super::{errors::ChipErrors, opcode::Opcode};
+use super::{
+    audio::{AudioPattern, PATTERN_BYTES},
+    errors::ChipErrors,
+    framebuffer::Framebuffer,
+    opcode::Opcode,
+    quirks::{IndexIncrement, Quirks},
+    random::Random,
+};
 
-pub const CHIP8_WIDTH: usize = 64;
-pub const CHIP8_HEIGHT: usize = 32;
-const START_ADDRESS: usize = 512;
+mod state;
+
+pub use state::SAVE_STATE_VERSION;
+
+#[cfg(test)]
+mod tests;
+
+/// Rate at which the delay and sound timers count down, in Hz.
+pub const TIMER_FREQUENCY: u32 = 60;
+/// XO-CHIP extends the address space to the full 16-bit range.
+pub const MEMORY_SIZE: usize = 0x10000;
+pub const START_ADDRESS: usize = 512;
 const FONT_START_ADDRESS: usize = 0x50;
+const BIG_FONT_START_ADDRESS: usize = FONT_START_ADDRESS + FONT.len();
 
 pub struct Chip8 {
     pc: u16,
     opcode: u16,
     I: u16,
     sp: u16,
-    memory: [u8; 4096],
+    memory: Vec<u8>,
     delay_timer: u8,
     sound_timer: u8,
-    gfx: [[u8; CHIP8_WIDTH]; CHIP8_HEIGHT],
+    gfx: Framebuffer,
     stack: [u16; 16],
     V: [u8; 16],
-    rnd: ThreadRng,
+    rnd: Random,
     keyboard_register: u8,
     keyboard_waiting: bool,
+    /// Key pressed during FX0A, which completes once it is released.
+    keyboard_pressed: Option<u8>,
+    flags: [u8; 16],
+    halted: bool,
+    planes: u8,
+    audio_pattern: AudioPattern,
+    pattern_loaded: bool,
+    accesses: Vec<MemoryAccess>,
+    quirks: Quirks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A data access (not an instruction fetch) made by the last executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub range: Range<usize>,
+    pub kind: AccessKind,
 }
 
 pub struct CycleResult<'a> {
-    pub gfx: &'a [[u8; CHIP8_WIDTH]; CHIP8_HEIGHT],
+    pub gfx: &'a Framebuffer,
     pub draw_update: bool,
+    pub sound_active: bool,
 }
 
 impl Chip8 {
-    pub fn new(program: Vec<u8>) -> Self {
-        let mut memory = [0; 4096];
+    pub fn new(program: Vec<u8>, quirks: Quirks) -> Result<Self, ChipErrors> {
+        let max = MEMORY_SIZE - START_ADDRESS;
+        if program.len() > max {
+            return Err(ChipErrors::RomTooLarge {
+                size: program.len(),
+                max,
+            });
+        }
+
+        let mut memory = vec![0; MEMORY_SIZE];
         for i in 0..FONT.len() {
             memory[FONT_START_ADDRESS + i] = FONT[i];
         }
 
+        memory[BIG_FONT_START_ADDRESS..BIG_FONT_START_ADDRESS + BIG_FONT.len()]
+            .copy_from_slice(&BIG_FONT);
+
         for i in 0..program.len() {
             memory[START_ADDRESS + i] = program[i];
         }
 
-        Chip8 {
+        Ok(Chip8 {
             pc: START_ADDRESS as u16,
             opcode: 0,
             I: 0,
@@ -49,265 +103,619 @@ impl Chip8 {
             memory,
             delay_timer: 0,
             sound_timer: 0,
-            gfx: [[0; 64]; 32],
+            gfx: Framebuffer::default(),
             stack: [0; 16],
             V: [0; 16],
-            rnd: rand::thread_rng(),
+            rnd: Random::from_entropy(),
             keyboard_register: 0,
             keyboard_waiting: false,
+            keyboard_pressed: None,
+            flags: [0; 16],
+            halted: false,
+            planes: 1,
+            audio_pattern: AudioPattern::default(),
+            pattern_loaded: false,
+            accesses: Vec::new(),
+            quirks,
+        })
+    }
+
+    /// Replaces the entropy-seeded CXNN generator with a deterministic one.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rnd = Random::new(seed);
+        self
+    }
+
+    /// Replaces the CXNN generator, e.g. to resume a recorded sequence.
+    pub fn with_random(mut self, rnd: Random) -> Self {
+        self.rnd = rnd;
+        self
+    }
+
+    /// Runs one 60 Hz frame: `cycles_per_frame` instructions followed by a
+    /// single timer tick. `draw_update` is set if any instruction in the
+    /// frame touched the display. With the `display_wait` quirk the frame
+    /// ends early after the first sprite draw.
+    pub fn run_frame(
+        &mut self,
+        keyboard: &Keyboard,
+        cycles_per_frame: u32,
+    ) -> Result<CycleResult<'_>, ChipErrors> {
+        let mut draw_update = false;
+        for _ in 0..cycles_per_frame {
+            draw_update |= self.emulateCycle(keyboard)?.draw_update;
+
+            if self.waits_for_display() {
+                break;
+            }
+        }
+
+        self.tick_timers();
+
+        Ok(CycleResult {
+            draw_update,
+            gfx: &self.gfx,
+            sound_active: self.sound_timer > 0,
+        })
+    }
+
+    /// Decrements the delay and sound timers. Must be called at
+    /// `TIMER_FREQUENCY`, independently of how many instructions are executed.
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
         }
     }
 
+    /// The XO-CHIP sample loop, once the program has loaded one with F002.
+    /// Programs that never do so should get a plain beep instead.
+    pub fn audio_pattern(&self) -> Option<AudioPattern> {
+        self.pattern_loaded.then_some(self.audio_pattern)
+    }
+
+    /// True once the program has executed 00FD.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// The most recently fetched instruction word.
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    pub fn index(&self) -> u16 {
+        self.I
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.V
+    }
+
+    /// The active part of the call stack, oldest return address first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.gfx
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// The CXNN generator, including its seed.
+    pub fn random(&self) -> &Random {
+        &self.rnd
+    }
+
+    /// True while FX0A is blocking on a key press and release.
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.keyboard_waiting
+    }
+
+    /// Data reads and writes made by the last executed instruction.
+    pub fn last_accesses(&self) -> &[MemoryAccess] {
+        &self.accesses
+    }
+
     pub fn emulateCycle(&mut self, keyboard: &Keyboard) -> Result<CycleResult, ChipErrors> {
-        if self.keyboard_waiting {
-            println!("Waiting keyboard: {keyboard:?}");
+        self.accesses.clear();
 
-            if let Some(key) = keyboard.get_pressed_key() {
-                self.keyboard_waiting = false;
-                self.V[self.keyboard_register as usize] = key;
+        if self.halted {
+            return Ok(CycleResult {
+                draw_update: false,
+                gfx: &self.gfx,
+                sound_active: self.sound_timer > 0,
+            });
+        }
+
+        // Like the COSMAC VIP, FX0A waits for a key to be pressed and then released.
+        if self.keyboard_waiting {
+            match self.keyboard_pressed {
+                None => self.keyboard_pressed = keyboard.first_pressed(),
+                Some(key) if !keyboard.is_pressed(key) => {
+                    self.keyboard_waiting = false;
+                    self.keyboard_pressed = None;
+                    self.V[self.keyboard_register as usize] = key;
+                }
+                Some(_) => {}
             }
 
             return Ok(CycleResult {
                 draw_update: false,
                 gfx: &self.gfx,
+                sound_active: self.sound_timer > 0,
             });
         }
 
-        self.opcode = (self.memory[self.pc as usize] as u16) << 8
-            | (self.memory[self.pc as usize + 1] as u16);
-        println!("Executing code {:04x}", self.opcode);
+        self.opcode = self.read_word(self.pc as usize)?;
         let operation = Opcode::parse(self.opcode)?;
         let mut draw_update = false;
 
         match operation {
             Opcode::SetI(value) => {
-                println!("Set I to {:03x}", value);
                 self.I = value;
-                self.pc += 2;
+                self.advance(2)?;
             }
             Opcode::SetVConstant(x, n) => {
                 self.V[x as usize] = n;
-                self.pc += 2;
+                self.advance(2)?;
             }
             Opcode::SetV(x, y) => {
                 self.V[x as usize] = self.V[y as usize];
-                self.pc += 2;
+                self.advance(2)?;
             }
             Opcode::Or(x, y) => {
                 self.V[x as usize] |= self.V[y as usize];
-                self.pc += 2;
+                if self.quirks.vf_reset {
+                    self.V[0xF] = 0;
+                }
+                self.advance(2)?;
             }
             Opcode::And(x, y) => {
                 self.V[x as usize] &= self.V[y as usize];
-                self.pc += 2;
+                if self.quirks.vf_reset {
+                    self.V[0xF] = 0;
+                }
+                self.advance(2)?;
             }
             Opcode::Xor(x, y) => {
                 self.V[x as usize] ^= self.V[y as usize];
-                self.pc += 2;
+                if self.quirks.vf_reset {
+                    self.V[0xF] = 0;
+                }
+                self.advance(2)?;
             }
-            Opcode::ShiftLeft(x) => {
-                self.V[0xF] = (self.V[x as usize] & 0b10000000) >> 7;
-                self.V[x as usize] = self.V[x as usize] << 1;
-                self.pc += 2;
+            Opcode::ShiftLeft(x, y) => {
+                let value = if self.quirks.shifting {
+                    self.V[x as usize]
+                } else {
+                    self.V[y as usize]
+                };
+                self.V[x as usize] = value << 1;
+                self.V[0xF] = (value & 0b10000000) >> 7;
+                self.advance(2)?;
             }
-            Opcode::ShiftRight(x) => {
-                self.V[0xF] = self.V[x as usize] & 0b1;
-                self.V[x as usize] = self.V[x as usize] >> 1;
-                self.pc += 2;
+            Opcode::ShiftRight(x, y) => {
+                let value = if self.quirks.shifting {
+                    self.V[x as usize]
+                } else {
+                    self.V[y as usize]
+                };
+                self.V[x as usize] = value >> 1;
+                self.V[0xF] = value & 0b1;
+                self.advance(2)?;
             }
             Opcode::SetDelayTimer(x) => {
                 self.delay_timer = self.V[x as usize];
-                self.pc += 2;
+                self.advance(2)?;
+            }
+            Opcode::SetSoundTimer(x) => {
+                self.sound_timer = self.V[x as usize];
+                self.advance(2)?;
             }
             Opcode::Dump(x) => {
-                for i in 0..=(x as usize) {
-                    self.memory[self.I as usize + i] = self.V[i];
-                }
-                self.pc += 2;
+                let range = self.data_range(self.I as usize, x as usize + 1, AccessKind::Write)?;
+                self.memory[range].copy_from_slice(&self.V[..=x as usize]);
+                self.increment_index(x)?;
+                self.advance(2)?;
             }
             Opcode::Load(x) => {
-                for i in 0..=(x as usize) {
-                    self.V[i] = self.memory[self.I as usize + i];
-                }
-                self.pc += 2;
+                let range = self.data_range(self.I as usize, x as usize + 1, AccessKind::Read)?;
+                self.V[..=x as usize].copy_from_slice(&self.memory[range]);
+                self.increment_index(x)?;
+                self.advance(2)?;
             }
             Opcode::SpriteAddress(x) => {
                 self.I = FONT_START_ADDRESS as u16 + self.V[x as usize] as u16 * 5;
-                self.pc += 2;
+                self.advance(2)?;
+            }
+            Opcode::BigSpriteAddress(x) => {
+                self.I = BIG_FONT_START_ADDRESS as u16 + self.V[x as usize] as u16 * 10;
+                self.advance(2)?;
+            }
+            Opcode::SaveFlags(x) => {
+                self.flags[..=x as usize].copy_from_slice(&self.V[..=x as usize]);
+                self.advance(2)?;
+            }
+            Opcode::LoadFlags(x) => {
+                self.V[..=x as usize].copy_from_slice(&self.flags[..=x as usize]);
+                self.advance(2)?;
+            }
+            Opcode::SetILong => {
+                self.I = self.read_word(self.pc as usize + 2)?;
+                self.advance(4)?;
+            }
+            Opcode::SelectPlanes(n) => {
+                self.planes = n & 0b11;
+                self.advance(2)?;
+            }
+            Opcode::LoadAudioPattern => {
+                let range = self.data_range(self.I as usize, PATTERN_BYTES, AccessKind::Read)?;
+                self.audio_pattern
+                    .buffer
+                    .copy_from_slice(&self.memory[range]);
+                self.pattern_loaded = true;
+                self.advance(2)?;
+            }
+            Opcode::SetPitch(x) => {
+                self.audio_pattern.pitch = self.V[x as usize];
+                self.advance(2)?;
+            }
+            Opcode::SaveRange(x, y) => {
+                let start = self
+                    .data_range(
+                        self.I as usize,
+                        x.abs_diff(y) as usize + 1,
+                        AccessKind::Write,
+                    )?
+                    .start;
+                for (offset, register) in Self::register_range(x, y).enumerate() {
+                    self.memory[start + offset] = self.V[register];
+                }
+                self.advance(2)?;
+            }
+            Opcode::LoadRange(x, y) => {
+                let start = self
+                    .data_range(
+                        self.I as usize,
+                        x.abs_diff(y) as usize + 1,
+                        AccessKind::Read,
+                    )?
+                    .start;
+                for (offset, register) in Self::register_range(x, y).enumerate() {
+                    self.V[register] = self.memory[start + offset];
+                }
+                self.advance(2)?;
             }
             Opcode::GetKey(x) => {
                 self.keyboard_waiting = true;
                 self.keyboard_register = x;
-                self.pc += 2;
+                self.advance(2)?;
             }
+            // The flag is written after the result, so it wins when X is F.
             Opcode::Subtract(x, y) => {
-                self.V[0xF] = if self.V[x as usize] > self.V[y as usize] {
-                    1
-                } else {
-                    0
-                };
-                self.V[x as usize] = self.V[x as usize].wrapping_sub(self.V[y as usize]);
-                self.pc += 2;
+                let (vx, vy) = (self.V[x as usize], self.V[y as usize]);
+                self.V[x as usize] = vx.wrapping_sub(vy);
+                self.V[0xF] = (vx >= vy) as u8;
+                self.advance(2)?;
             }
             Opcode::SubtractOpposite(x, y) => {
-                self.V[0xF] = if self.V[x as usize] < self.V[y as usize] {
-                    1
-                } else {
-                    0
-                };
-                self.V[x as usize] = self.V[y as usize].wrapping_sub(self.V[x as usize]);
-                self.pc += 2;
+                let (vx, vy) = (self.V[x as usize], self.V[y as usize]);
+                self.V[x as usize] = vy.wrapping_sub(vx);
+                self.V[0xF] = (vy >= vx) as u8;
+                self.advance(2)?;
             }
             Opcode::ClearScreen => {
-                println!("Clearing screen");
-                self.pc += 2;
-                self.gfx = [[0; CHIP8_WIDTH]; CHIP8_HEIGHT];
+                self.advance(2)?;
+                self.gfx.clear(self.planes);
+                draw_update = true;
+            }
+            Opcode::ScrollDown(n) => {
+                self.gfx.scroll_down(n as usize, self.planes);
+                self.advance(2)?;
+                draw_update = true;
+            }
+            Opcode::ScrollUp(n) => {
+                self.gfx.scroll_up(n as usize, self.planes);
+                self.advance(2)?;
+                draw_update = true;
+            }
+            Opcode::ScrollRight => {
+                self.gfx.scroll_right(4, self.planes);
+                self.advance(2)?;
+                draw_update = true;
+            }
+            Opcode::ScrollLeft => {
+                self.gfx.scroll_left(4, self.planes);
+                self.advance(2)?;
+                draw_update = true;
+            }
+            Opcode::Exit => {
+                self.halted = true;
+            }
+            Opcode::LowResolution => {
+                self.gfx.set_hires(false);
+                self.advance(2)?;
+                draw_update = true;
+            }
+            Opcode::HighResolution => {
+                self.gfx.set_hires(true);
+                self.advance(2)?;
                 draw_update = true;
             }
             Opcode::ReturnFromSubroutine => {
+                if self.sp == 0 {
+                    return Err(ChipErrors::StackUnderflow { pc: self.pc });
+                }
+
                 self.sp -= 1;
                 self.pc = self.stack[self.sp as usize];
             }
             Opcode::CallSubroutine(addr) => {
-                self.stack[self.sp as usize] = self.pc + 2;
+                if self.sp as usize == self.stack.len() {
+                    return Err(ChipErrors::StackOverflow { pc: self.pc });
+                }
+
+                self.stack[self.sp as usize] = self.next_address(2)?;
                 self.sp += 1;
                 self.pc = addr;
             }
             Opcode::Add(x, y) => {
                 let sum = self.V[x as usize] as u16 + self.V[y as usize] as u16;
-
-                if sum > 0xFF {
-                    self.V[0xF] = 1;
-                } else {
-                    self.V[0xF] = 0;
-                }
-
                 self.V[x as usize] = (sum & 0xFF) as u8;
-                self.pc += 2;
+                self.V[0xF] = (sum > 0xFF) as u8;
+                self.advance(2)?;
             }
             Opcode::AddConstant(x, n) => {
                 self.V[x as usize] = self.V[x as usize].wrapping_add(n);
-                self.pc += 2;
+                self.advance(2)?;
             }
             Opcode::RandAnd(x, n) => {
-                self.V[x as usize] = self.rnd.gen::<u8>() & n;
-                self.pc += 2;
+                self.V[x as usize] = self.rnd.next_byte() & n;
+                self.advance(2)?;
             }
             Opcode::GetDelayTimer(x) => {
                 self.V[x as usize] = self.delay_timer;
-                self.pc += 2;
+                self.advance(2)?;
             }
             Opcode::AddMemory(x) => {
-                println!(
-                    "AddMemory x={:02x}. Before Vx={:02x}, I={:04x}",
-                    x, self.V[x as usize], self.I
-                );
-                self.I += self.V[x as usize] as u16;
-                println!("After I={:04x}", self.I);
-                self.pc += 2;
+                let addr = self.I as usize + self.V[x as usize] as usize;
+                self.I = u16::try_from(addr)
+                    .map_err(|_| ChipErrors::MemoryOutOfBounds { addr, pc: self.pc })?;
+                self.advance(2)?;
             }
             Opcode::Jump(addr) => {
                 self.pc = addr;
             }
             Opcode::JumpPlus(addr) => {
-                self.pc = addr + self.V[0] as u16;
+                let register = if self.quirks.jumping {
+                    (addr >> 8) & 0xF
+                } else {
+                    0
+                };
+                self.pc = addr + self.V[register as usize] as u16;
             }
             Opcode::BinaryCodedDecimal(x) => {
                 let value = self.V[x as usize];
-                self.memory[self.I as usize] = value / 100 % 10;
-                self.memory[self.I as usize + 1] = value / 10 % 10;
-                self.memory[self.I as usize + 2] = value % 10;
-                self.pc += 2;
+                let range = self.data_range(self.I as usize, 3, AccessKind::Write)?;
+                self.memory[range].copy_from_slice(&[
+                    value / 100 % 10,
+                    value / 10 % 10,
+                    value % 10,
+                ]);
+                self.advance(2)?;
             }
             Opcode::SkipRegistersEqual(x, y) => {
                 if self.V[x as usize] == self.V[y as usize] {
-                    self.pc += 2;
+                    self.skip_instruction()?;
+                } else {
+                    self.advance(2)?;
                 }
-
-                self.pc += 2;
             }
             Opcode::SkipRegistersNonEqual(x, y) => {
                 if self.V[x as usize] != self.V[y as usize] {
-                    self.pc += 2;
+                    self.skip_instruction()?;
+                } else {
+                    self.advance(2)?;
                 }
-
-                self.pc += 2;
             }
             Opcode::SkipEqual(x, n) => {
                 if self.V[x as usize] == n {
-                    self.pc += 2;
+                    self.skip_instruction()?;
+                } else {
+                    self.advance(2)?;
                 }
-
-                self.pc += 2;
             }
             Opcode::SkipNonEqual(x, n) => {
                 if self.V[x as usize] != n {
-                    self.pc += 2;
+                    self.skip_instruction()?;
+                } else {
+                    self.advance(2)?;
                 }
-
-                self.pc += 2;
             }
             Opcode::SkipKeyEqual(x) => {
-                if let Some(key) = keyboard.get_pressed_key() {
-                    if key == self.V[x as usize] {
-                        self.pc += 2;
-                    }
+                if keyboard.is_pressed(self.V[x as usize]) {
+                    self.skip_instruction()?;
+                } else {
+                    self.advance(2)?;
                 }
-
-                self.pc += 2;
             }
             Opcode::SkipKeyNonEqual(x) => {
-                if let Some(key) = keyboard.get_pressed_key() {
-                    if key != self.V[x as usize] {
-                        self.pc += 2;
-                    }
+                if !keyboard.is_pressed(self.V[x as usize]) {
+                    self.skip_instruction()?;
+                } else {
+                    self.advance(2)?;
                 }
-
-                self.pc += 2;
             }
             Opcode::Draw(x, y, n) => {
-                println!(
-                    "x={:02x}, y={:02x}, n={:02x}, Vx={:02x}, Vy={:02x}",
-                    x, y, n, self.V[x as usize], self.V[y as usize]
-                );
                 self.V[0xF] = 0;
-                for line in 0..n {
-                    let pixel = self.memory[(self.I + line as u16) as usize];
-
-                    for column in 0..8 {
-                        if (pixel & (0x80 >> column)) != 0 {
-                            let x = (self.V[x as usize] as usize + column as usize) as usize
-                                % CHIP8_WIDTH;
-                            let y = (self.V[y as usize] as usize + line as usize) as usize
-                                % CHIP8_HEIGHT;
-                            if self.gfx[y][x] == 1 {
-                                self.V[0xF] = 1;
+                let width = self.gfx.width();
+                let height = self.gfx.height();
+                let start_x = self.V[x as usize] as usize % width;
+                let start_y = self.V[y as usize] as usize % height;
+                // DXY0 draws a 16x16 sprite made of two bytes per row.
+                let (rows, columns) = if n == 0 { (16, 16) } else { (n as usize, 8) };
+                let bytes_per_row = columns / 8;
+                let planes = (self.planes & 0b01) + (self.planes >> 1 & 0b01);
+                let mut address = self
+                    .data_range(
+                        self.I as usize,
+                        rows * bytes_per_row * planes as usize,
+                        AccessKind::Read,
+                    )?
+                    .start;
+
+                // Each selected XO-CHIP plane consumes its own copy of the sprite data.
+                for plane in [0b01, 0b10] {
+                    if self.planes & plane == 0 {
+                        continue;
+                    }
+
+                    for line in 0..rows {
+                        let pixels = if bytes_per_row == 2 {
+                            (self.memory[address] as u16) << 8 | self.memory[address + 1] as u16
+                        } else {
+                            (self.memory[address] as u16) << 8
+                        };
+                        address += bytes_per_row;
+
+                        for column in 0..columns {
+                            if (pixels & (0x8000 >> column)) != 0 {
+                                let x = start_x + column;
+                                let y = start_y + line;
+                                if self.quirks.clipping && (x >= width || y >= height) {
+                                    continue;
+                                }
+
+                                if self.gfx.toggle(x % width, y % height, plane) {
+                                    self.V[0xF] = 1;
+                                }
                             }
-                            self.gfx[y][x] ^= 1;
                         }
                     }
                 }
 
-                self.pc += 2;
+                self.advance(2)?;
                 draw_update = true;
             }
         }
 
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
-
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
-
-            if self.sound_timer == 0 {
-                println!("BEEP");
-            }
-        }
-
         Ok(CycleResult {
             draw_update,
             gfx: &self.gfx,
+            sound_active: self.sound_timer > 0,
         })
     }
+
+    /// True if the last instruction was a sprite draw and the `display_wait`
+    /// quirk requires the rest of the frame to be skipped.
+    pub(crate) fn waits_for_display(&self) -> bool {
+        self.quirks.display_wait && self.opcode & 0xF000 == 0xD000
+    }
+
+    fn increment_index(&mut self, x: u8) -> Result<(), ChipErrors> {
+        let step = match self.quirks.memory {
+            IndexIncrement::Unchanged => 0,
+            IndexIncrement::X => x as usize,
+            IndexIncrement::XPlusOne => x as usize + 1,
+        };
+
+        let addr = self.I as usize + step;
+        self.I =
+            u16::try_from(addr).map_err(|_| ChipErrors::MemoryOutOfBounds { addr, pc: self.pc })?;
+        Ok(())
+    }
+
+    /// Moves past the current instruction and skips the next one, which is
+    /// four bytes long if it is F000 NNNN.
+    fn skip_instruction(&mut self) -> Result<(), ChipErrors> {
+        let next_opcode = self.read_word(self.pc as usize + 2)?;
+        self.advance(if next_opcode == 0xF000 { 6 } else { 4 })
+    }
+
+    /// The address `bytes` past the current instruction, which must still
+    /// be addressable.
+    fn next_address(&self, bytes: u16) -> Result<u16, ChipErrors> {
+        self.pc
+            .checked_add(bytes)
+            .ok_or(ChipErrors::MemoryOutOfBounds {
+                addr: self.pc as usize + bytes as usize,
+                pc: self.pc,
+            })
+    }
+
+    /// Moves `bytes` forward from the current instruction.
+    fn advance(&mut self, bytes: u16) -> Result<(), ChipErrors> {
+        self.pc = self.next_address(bytes)?;
+        Ok(())
+    }
+
+    /// Validates that `len` bytes starting at `start` lie inside memory.
+    fn memory_range(&self, start: usize, len: usize) -> Result<Range<usize>, ChipErrors> {
+        let end = start + len;
+        if end > self.memory.len() {
+            return Err(ChipErrors::MemoryOutOfBounds {
+                addr: start.max(self.memory.len()),
+                pc: self.pc,
+            });
+        }
+
+        Ok(start..end)
+    }
+
+    /// Like `memory_range`, but also records the access for watchpoints.
+    fn data_range(
+        &mut self,
+        start: usize,
+        len: usize,
+        kind: AccessKind,
+    ) -> Result<Range<usize>, ChipErrors> {
+        let range = self.memory_range(start, len)?;
+        self.accesses.push(MemoryAccess {
+            range: range.clone(),
+            kind,
+        });
+        Ok(range)
+    }
+
+    fn read_word(&self, addr: usize) -> Result<u16, ChipErrors> {
+        let range = self.memory_range(addr, 2)?;
+        Ok((self.memory[range.start] as u16) << 8 | self.memory[range.start + 1] as u16)
+    }
+
+    /// Registers touched by 5XY2/5XY3, in order from VX to VY in either direction.
+    fn register_range(x: u8, y: u8) -> Box<dyn Iterator<Item = usize>> {
+        let (x, y) = (x as usize, y as usize);
+        if x <= y {
+            Box::new(x..=y)
+        } else {
+            Box::new((y..=x).rev())
+        }
+    }
 }
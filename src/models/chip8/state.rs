@@ -0,0 +1,183 @@
+//! Versioned binary snapshots of the whole machine.
+//!
+//! Layout, all integers little-endian:
+//!
+//! | field            | size                      |
+//! |------------------|---------------------------|
+//! | magic `CH8STATE` | 8                         |
+//! | version          | 2                         |
+//! | pc, opcode, I, sp| 2 each                    |
+//! | V0..VF           | 16                        |
+//! | stack            | 16 x 2                    |
+//! | delay, sound     | 1 each                    |
+//! | key register     | 1                         |
+//! | key waiting      | 1                         |
+//! | flags            | 16                        |
+//! | halted, planes   | 1 each                    |
+//! | audio pattern    | 16 + pitch 1 + loaded 1   |
+//! | quirks           | 6                         |
+//! | RNG seed, state  | 8 each                    |
+//! | FX0A pressed key | 1, 0xFF if none           |
+//! | screen           | width 2, height 2, pixels |
+//! | memory           | `MEMORY_SIZE`             |
+//! | CRC-32           | 4                         |
+
+use crate::{
+    bytes::Reader,
+    hash::crc32,
+    models::{
+        audio::{AudioPattern, PATTERN_BYTES},
+        errors::ChipErrors,
+        framebuffer::Framebuffer,
+        quirks::Quirks,
+        random::Random,
+    },
+};
+
+use super::{Chip8, MEMORY_SIZE};
+
+const MAGIC: &[u8; 8] = b"CH8STATE";
+pub const SAVE_STATE_VERSION: u16 = 1;
+/// Stored in place of the FX0A key while none has been pressed yet.
+const NO_KEY: u8 = 0xFF;
+
+impl Chip8 {
+    /// Serializes every piece of machine state into a self-checking snapshot.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MEMORY_SIZE + 512);
+        out.extend_from_slice(MAGIC);
+        put_u16(&mut out, SAVE_STATE_VERSION);
+
+        put_u16(&mut out, self.pc);
+        put_u16(&mut out, self.opcode);
+        put_u16(&mut out, self.I);
+        put_u16(&mut out, self.sp);
+        out.extend_from_slice(&self.V);
+        for addr in self.stack {
+            put_u16(&mut out, addr);
+        }
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.push(self.keyboard_register);
+        out.push(self.keyboard_waiting as u8);
+        out.extend_from_slice(&self.flags);
+        out.push(self.halted as u8);
+        out.push(self.planes);
+        out.extend_from_slice(&self.audio_pattern.buffer);
+        out.push(self.audio_pattern.pitch);
+        out.push(self.pattern_loaded as u8);
+
+        out.extend_from_slice(&self.quirks.to_bytes());
+        out.extend_from_slice(&self.rnd.seed().to_le_bytes());
+        out.extend_from_slice(&self.rnd.state().to_le_bytes());
+        out.push(self.keyboard_pressed.unwrap_or(NO_KEY));
+
+        put_u16(&mut out, self.gfx.width() as u16);
+        put_u16(&mut out, self.gfx.height() as u16);
+        out.extend_from_slice(self.gfx.pixels());
+        out.extend_from_slice(&self.memory);
+
+        let checksum = crc32(&out);
+        out.extend_from_slice(&checksum.to_le_bytes());
+        out
+    }
+
+    /// Restores a snapshot made by `save_state`. The machine is left
+    /// untouched if the snapshot is damaged or from an unknown version.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), ChipErrors> {
+        let (body, checksum) = data
+            .split_last_chunk::<4>()
+            .ok_or_else(|| invalid("truncated"))?;
+        if crc32(body) != u32::from_le_bytes(*checksum) {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        let mut reader = Reader::new(body, ChipErrors::InvalidSaveState);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a save state"));
+        }
+        let version = reader.u16()?;
+        if version != SAVE_STATE_VERSION {
+            return Err(ChipErrors::UnsupportedSaveStateVersion(version));
+        }
+
+        let pc = reader.u16()?;
+        let opcode = reader.u16()?;
+        let index = reader.u16()?;
+        let sp = reader.u16()?;
+        let registers = reader.array::<16>()?;
+        let mut stack = [0; 16];
+        for addr in stack.iter_mut() {
+            *addr = reader.u16()?;
+        }
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let keyboard_register = reader.u8()?;
+        let keyboard_waiting = reader.bool()?;
+        let flags = reader.array::<16>()?;
+        let halted = reader.bool()?;
+        let planes = reader.u8()?;
+        let audio_pattern = AudioPattern {
+            buffer: reader.array::<PATTERN_BYTES>()?,
+            pitch: reader.u8()?,
+        };
+        let pattern_loaded = reader.bool()?;
+
+        let quirks = Quirks::from_bytes(reader.array()?).ok_or_else(|| invalid("bad quirks"))?;
+        let rnd = Random::restore(reader.u64()?, reader.u64()?);
+        let keyboard_pressed = match reader.u8()? {
+            NO_KEY => None,
+            key => Some(key),
+        };
+
+        let width = reader.u16()? as usize;
+        let height = reader.u16()? as usize;
+        let pixels = reader.take(width * height)?.to_vec();
+        let gfx =
+            Framebuffer::from_pixels(width, height, pixels).ok_or_else(|| invalid("bad screen"))?;
+        let memory = reader.take(MEMORY_SIZE)?.to_vec();
+
+        if !reader.is_empty() {
+            return Err(invalid("trailing data"));
+        }
+        if sp as usize > stack.len()
+            || keyboard_register > 0xF
+            || keyboard_pressed.is_some_and(|key| key > 0xF)
+            || planes > 3
+        {
+            return Err(invalid("register out of range"));
+        }
+
+        self.pc = pc;
+        self.opcode = opcode;
+        self.I = index;
+        self.sp = sp;
+        self.V = registers;
+        self.stack = stack;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.keyboard_register = keyboard_register;
+        self.keyboard_waiting = keyboard_waiting;
+        self.keyboard_pressed = keyboard_pressed;
+        self.flags = flags;
+        self.halted = halted;
+        self.planes = planes;
+        self.audio_pattern = audio_pattern;
+        self.pattern_loaded = pattern_loaded;
+        self.quirks = quirks;
+        self.rnd = rnd;
+        self.gfx = gfx;
+        self.memory = memory;
+        self.accesses.clear();
+
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> ChipErrors {
+    ChipErrors::InvalidSaveState(reason.to_string())
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
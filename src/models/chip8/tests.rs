@@ -0,0 +1,731 @@
+use super::*;
+use crate::input::keyboard::Key;
+
+const START: u16 = START_ADDRESS as u16;
+
+fn chip(program: &[u16]) -> Chip8 {
+    chip_with(program, Quirks::COSMAC_VIP)
+}
+
+fn chip_with(program: &[u16], quirks: Quirks) -> Chip8 {
+    let rom = program.iter().flat_map(|word| word.to_be_bytes()).collect();
+    Chip8::new(rom, quirks).unwrap().with_seed(0)
+}
+
+fn step(chip: &mut Chip8) {
+    step_with(chip, &Keyboard::new());
+}
+
+fn step_with(chip: &mut Chip8, keyboard: &Keyboard) {
+    chip.emulateCycle(keyboard).unwrap();
+}
+
+fn pressed(key: Key) -> Keyboard {
+    let mut keyboard = Keyboard::new();
+    keyboard.press(key);
+    keyboard
+}
+
+fn lit_pixels(chip: &Chip8) -> Vec<(usize, usize, u8)> {
+    let gfx = chip.framebuffer();
+    (0..gfx.height())
+        .flat_map(|y| (0..gfx.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| gfx.get(x, y) != 0)
+        .map(|(x, y)| (x, y, gfx.get(x, y)))
+        .collect()
+}
+
+#[test]
+fn set_i() {
+    let mut chip = chip(&[0xA123]);
+    step(&mut chip);
+    assert_eq!(chip.index(), 0x123);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn set_v_constant() {
+    let mut chip = chip(&[0x6A42]);
+    step(&mut chip);
+    assert_eq!(chip.registers()[0xA], 0x42);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn set_v() {
+    let mut chip = chip(&[0x8120]);
+    chip.V[2] = 7;
+    step(&mut chip);
+    assert_eq!(chip.registers()[1], 7);
+    assert_eq!(chip.registers()[2], 7);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn clear_screen() {
+    let mut chip = chip(&[0x00E0]);
+    chip.gfx.toggle(3, 4, 1);
+    step(&mut chip);
+    assert!(lit_pixels(&chip).is_empty());
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn call_and_return() {
+    let mut chip = chip(&[0x2206, 0x0000, 0x0000, 0x00EE]);
+    step(&mut chip);
+    assert_eq!(chip.pc(), 0x206);
+    assert_eq!(chip.stack(), &[START + 2]);
+
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 2);
+    assert!(chip.stack().is_empty());
+}
+
+#[test]
+fn return_with_empty_stack_underflows() {
+    let mut chip = chip(&[0x00EE]);
+    assert!(matches!(
+        chip.emulateCycle(&Keyboard::new()),
+        Err(ChipErrors::StackUnderflow { pc: START })
+    ));
+}
+
+#[test]
+fn instruction_at_end_of_memory_faults_instead_of_wrapping() {
+    let mut last = chip(&[]);
+    last.pc = 0xFFFE;
+    last.memory[0xFFFE..].copy_from_slice(&[0x60, 0x01]);
+    assert!(matches!(
+        last.emulateCycle(&Keyboard::new()),
+        Err(ChipErrors::MemoryOutOfBounds {
+            addr: 0x10000,
+            pc: 0xFFFE
+        })
+    ));
+
+    // A skip over the last instruction and a call from it fault the same way.
+    for (code, pc) in [([0x30, 0x00], 0xFFFC), ([0x22, 0x00], 0xFFFE)] {
+        let mut chip = chip(&[]);
+        chip.pc = pc;
+        chip.memory[pc as usize..pc as usize + 2].copy_from_slice(&code);
+        assert!(matches!(
+            chip.emulateCycle(&Keyboard::new()),
+            Err(ChipErrors::MemoryOutOfBounds { pc: fault, .. }) if fault == pc
+        ));
+    }
+}
+
+#[test]
+fn call_past_stack_depth_overflows() {
+    let mut chip = chip(&[0x2200]);
+    for _ in 0..16 {
+        step(&mut chip);
+    }
+    assert!(matches!(
+        chip.emulateCycle(&Keyboard::new()),
+        Err(ChipErrors::StackOverflow { pc: START })
+    ));
+}
+
+#[test]
+fn add_sets_carry() {
+    let mut chip = chip(&[0x8014, 0x8014]);
+    chip.V[0] = 0xF0;
+    chip.V[1] = 0x0F;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0xFF);
+    assert_eq!(chip.registers()[0xF], 0);
+
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0x0E);
+    assert_eq!(chip.registers()[0xF], 1);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn subtract_sets_no_borrow() {
+    let mut chip = chip(&[0x8015, 0x8015, 0x8015]);
+    chip.V[0] = 5;
+    chip.V[1] = 3;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 2);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0xFF);
+    assert_eq!(chip.registers()[0xF], 0);
+
+    chip.V[0] = 3;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0);
+    assert_eq!(chip.registers()[0xF], 1);
+}
+
+#[test]
+fn subtract_opposite_sets_no_borrow() {
+    let mut chip = chip(&[0x8017, 0x8017]);
+    chip.V[0] = 3;
+    chip.V[1] = 5;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 2);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    chip.V[0] = 6;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0xFF);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn flag_wins_when_vf_is_the_destination() {
+    let mut chip = chip(&[0x8F14, 0x8F15, 0x8F06]);
+    chip.V[0xF] = 0xFF;
+    chip.V[0] = 1;
+    chip.V[1] = 2;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    step(&mut chip);
+    assert_eq!(chip.registers()[0xF], 0);
+
+    step(&mut chip);
+    assert_eq!(chip.registers()[0xF], 1);
+}
+
+#[test]
+fn shift_right_reads_vy_on_vip() {
+    let mut chip = chip(&[0x8016]);
+    chip.V[0] = 0xFF;
+    chip.V[1] = 0b101;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0b10);
+    assert_eq!(chip.registers()[0xF], 1);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn shift_right_in_place_with_shifting_quirk() {
+    let mut chip = chip_with(&[0x8016], Quirks::SUPER_CHIP);
+    chip.V[0] = 0b100;
+    chip.V[1] = 0xFF;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0b10);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn shift_left_reads_vy_on_vip() {
+    let mut chip = chip(&[0x801E]);
+    chip.V[0] = 0;
+    chip.V[1] = 0x81;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0x02);
+    assert_eq!(chip.registers()[0xF], 1);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn shift_left_in_place_with_shifting_quirk() {
+    let mut chip = chip_with(&[0x801E], Quirks::SUPER_CHIP);
+    chip.V[0] = 0x41;
+    chip.V[1] = 0xFF;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0x82);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn logic_operations() {
+    let mut chip = chip_with(&[0x8011, 0x8012, 0x8013], Quirks::SUPER_CHIP);
+    chip.V[0] = 0b1100;
+    chip.V[1] = 0b1010;
+    chip.V[0xF] = 7;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0b1110);
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0b1010);
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0);
+    assert_eq!(chip.registers()[0xF], 7);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn logic_operations_reset_vf_on_vip() {
+    for opcode in [0x8011, 0x8012, 0x8013] {
+        let mut chip = chip(&[opcode]);
+        chip.V[0xF] = 7;
+        step(&mut chip);
+        assert_eq!(chip.registers()[0xF], 0, "{opcode:04X}");
+    }
+}
+
+#[test]
+fn add_constant_wraps_without_carry() {
+    let mut chip = chip(&[0x70FF]);
+    chip.V[0] = 2;
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 1);
+    assert_eq!(chip.registers()[0xF], 0);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn binary_coded_decimal() {
+    let mut chip = chip(&[0xF033]);
+    chip.V[0] = 254;
+    chip.I = 0x300;
+    step(&mut chip);
+    assert_eq!(&chip.memory()[0x300..0x303], &[2, 5, 4]);
+    assert_eq!(chip.index(), 0x300);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn skip_if_equal() {
+    let mut chip = chip(&[0x3005, 0x0000, 0x3005]);
+    chip.V[0] = 5;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 4);
+
+    chip.V[0] = 6;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn skip_if_not_equal() {
+    let mut chip = chip(&[0x4005, 0x0000, 0x4005]);
+    chip.V[0] = 6;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 4);
+
+    chip.V[0] = 5;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn skip_if_registers_equal() {
+    let mut chip = chip(&[0x5010, 0x0000, 0x5010]);
+    chip.V[0] = 3;
+    chip.V[1] = 3;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 4);
+
+    chip.V[1] = 4;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn skip_if_registers_not_equal() {
+    let mut chip = chip(&[0x9010, 0x0000, 0x9010]);
+    chip.V[0] = 3;
+    chip.V[1] = 4;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 4);
+
+    chip.V[1] = 3;
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn skip_jumps_over_long_load() {
+    let mut chip = chip(&[0x3000, 0xF000, 0x1234]);
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn skip_if_key_pressed() {
+    let mut chip = chip(&[0xE09E, 0x0000, 0xE09E]);
+    chip.V[0] = 5;
+    step_with(&mut chip, &pressed(Key::Key5));
+    assert_eq!(chip.pc(), START + 4);
+
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 6);
+}
+
+#[test]
+fn skip_if_key_pressed_among_others() {
+    let mut chip = chip(&[0xE09E]);
+    chip.V[0] = 5;
+    step_with(&mut chip, &Keyboard::from_mask(0b1111_1111_1111_1111));
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn skip_if_key_not_pressed() {
+    let mut chip = chip(&[0xE0A1, 0x0000, 0xE0A1, 0x0000, 0xE0A1]);
+    chip.V[0] = 5;
+    step_with(&mut chip, &pressed(Key::Key6));
+    assert_eq!(chip.pc(), START + 4);
+
+    step(&mut chip);
+    assert_eq!(chip.pc(), START + 8);
+
+    let mut keyboard = pressed(Key::Key5);
+    keyboard.press(Key::Key6);
+    step_with(&mut chip, &keyboard);
+    assert_eq!(chip.pc(), START + 10);
+}
+
+#[test]
+fn draw_sprite_and_detect_collision() {
+    // Font glyph 0 is 0xF0 0x90 0x90 0x90 0xF0.
+    let mut chip = chip(&[0xD015, 0xD015]);
+    chip.I = FONT_START_ADDRESS as u16;
+    chip.V[0] = 2;
+    chip.V[1] = 3;
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip).len(), 14);
+    assert!(lit_pixels(&chip).contains(&(2, 3, 1)));
+    assert_eq!(chip.registers()[0xF], 0);
+
+    step(&mut chip);
+    assert!(lit_pixels(&chip).is_empty());
+    assert_eq!(chip.registers()[0xF], 1);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn draw_clips_at_the_edge_on_vip() {
+    let mut chip = chip(&[0xD011]);
+    chip.I = FONT_START_ADDRESS as u16;
+    chip.V[0] = 62;
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip), vec![(62, 0, 1), (63, 0, 1)]);
+}
+
+#[test]
+fn draw_wraps_around_the_edge_without_clipping() {
+    let mut chip = chip_with(&[0xD011], Quirks::OCTO);
+    chip.I = FONT_START_ADDRESS as u16;
+    chip.V[0] = 62;
+    step(&mut chip);
+    assert_eq!(
+        lit_pixels(&chip),
+        vec![(0, 0, 1), (1, 0, 1), (62, 0, 1), (63, 0, 1)]
+    );
+}
+
+#[test]
+fn draw_large_sprite() {
+    let mut chip = chip(&[0x00FF, 0xD010]);
+    chip.I = 0x300;
+    chip.memory[0x300..0x320].fill(0xFF);
+    step(&mut chip);
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip).len(), 256);
+}
+
+#[test]
+fn jump() {
+    let mut chip = chip(&[0x1ABC]);
+    step(&mut chip);
+    assert_eq!(chip.pc(), 0xABC);
+}
+
+#[test]
+fn jump_plus_v0() {
+    let mut chip = chip(&[0xB300]);
+    chip.V[0] = 4;
+    chip.V[3] = 8;
+    step(&mut chip);
+    assert_eq!(chip.pc(), 0x304);
+}
+
+#[test]
+fn jump_plus_vx_with_jumping_quirk() {
+    let mut chip = chip_with(&[0xB300], Quirks::SUPER_CHIP);
+    chip.V[0] = 4;
+    chip.V[3] = 8;
+    step(&mut chip);
+    assert_eq!(chip.pc(), 0x308);
+}
+
+#[test]
+fn delay_timer() {
+    let mut chip = chip(&[0xF015, 0xF107]);
+    chip.V[0] = 9;
+    step(&mut chip);
+    assert_eq!(chip.delay_timer(), 9);
+
+    chip.tick_timers();
+    step(&mut chip);
+    assert_eq!(chip.registers()[1], 8);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn sound_timer() {
+    let mut chip = chip(&[0xF018]);
+    chip.V[0] = 3;
+    step(&mut chip);
+    assert_eq!(chip.sound_timer(), 3);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn dump_and_load_advance_i_on_vip() {
+    let mut chip = chip(&[0xF255, 0xF265]);
+    chip.I = 0x300;
+    chip.V[..3].copy_from_slice(&[1, 2, 3]);
+    step(&mut chip);
+    assert_eq!(&chip.memory()[0x300..0x303], &[1, 2, 3]);
+    assert_eq!(chip.index(), 0x303);
+
+    chip.I = 0x300;
+    chip.V[..3].fill(0);
+    step(&mut chip);
+    assert_eq!(&chip.registers()[..3], &[1, 2, 3]);
+    assert_eq!(chip.index(), 0x303);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn dump_index_increment_quirks() {
+    for (quirks, expected) in [
+        (Quirks::SUPER_CHIP, 0x300),
+        (Quirks::CHIP_48, 0x302),
+        (Quirks::OCTO, 0x303),
+    ] {
+        let mut chip = chip_with(&[0xF255], quirks);
+        chip.I = 0x300;
+        step(&mut chip);
+        assert_eq!(chip.index(), expected, "{quirks:?}");
+    }
+}
+
+#[test]
+fn sprite_address() {
+    let mut chip = chip(&[0xF029]);
+    chip.V[0] = 0xA;
+    step(&mut chip);
+    assert_eq!(chip.index() as usize, FONT_START_ADDRESS + 50);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn big_sprite_address() {
+    let mut chip = chip(&[0xF030]);
+    chip.V[0] = 3;
+    step(&mut chip);
+    assert_eq!(chip.index() as usize, BIG_FONT_START_ADDRESS + 30);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn random_is_masked_and_seeded() {
+    let mut chip = chip(&[0xC00F, 0xC10F]);
+    step(&mut chip);
+    step(&mut chip);
+
+    let mut expected = Random::new(0);
+    assert_eq!(chip.registers()[0], expected.next_byte() & 0x0F);
+    assert_eq!(chip.registers()[1], expected.next_byte() & 0x0F);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn add_to_index() {
+    let mut chip = chip(&[0xF01E]);
+    chip.I = 0x300;
+    chip.V[0] = 0x20;
+    step(&mut chip);
+    assert_eq!(chip.index(), 0x320);
+    assert_eq!(chip.registers()[0xF], 0);
+    assert_eq!(chip.pc(), START + 2);
+}
+
+#[test]
+fn get_key_waits_for_press_and_release() {
+    let mut chip = chip(&[0xF30A, 0x6001]);
+    step(&mut chip);
+    assert!(chip.is_waiting_for_key());
+
+    step(&mut chip);
+    assert!(chip.is_waiting_for_key());
+
+    step_with(&mut chip, &pressed(Key::KeyB));
+    step_with(&mut chip, &pressed(Key::KeyB));
+    assert!(chip.is_waiting_for_key());
+    assert_eq!(chip.registers()[3], 0);
+
+    step(&mut chip);
+    assert!(!chip.is_waiting_for_key());
+    assert_eq!(chip.registers()[3], 0xB);
+    assert_eq!(chip.pc(), START + 2);
+
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 1);
+}
+
+#[test]
+fn get_key_ignores_other_keys_until_release() {
+    let mut chip = chip(&[0xF30A]);
+    step(&mut chip);
+    step_with(&mut chip, &pressed(Key::Key2));
+
+    // Key 2 is released while key 7 is pressed: 2 completes the wait.
+    step_with(&mut chip, &pressed(Key::Key7));
+    assert!(!chip.is_waiting_for_key());
+    assert_eq!(chip.registers()[3], 2);
+}
+
+#[test]
+fn scrolls() {
+    let mut chip = chip(&[0x00C2, 0x00DA, 0x00FB, 0x00FC]);
+    chip.gfx.toggle(10, 10, 1);
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip), vec![(10, 12, 1)]);
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip), vec![(10, 2, 1)]);
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip), vec![(14, 2, 1)]);
+    step(&mut chip);
+    assert_eq!(lit_pixels(&chip), vec![(10, 2, 1)]);
+    assert_eq!(chip.pc(), START + 8);
+}
+
+#[test]
+fn exit_halts() {
+    let mut chip = chip(&[0x00FD, 0x6001]);
+    step(&mut chip);
+    assert!(chip.is_halted());
+
+    step(&mut chip);
+    assert_eq!(chip.registers()[0], 0);
+}
+
+#[test]
+fn resolution_switches() {
+    let mut chip = chip(&[0x00FF, 0x00FE]);
+    step(&mut chip);
+    assert!(chip.framebuffer().is_hires());
+    assert_eq!(chip.framebuffer().width(), 128);
+
+    step(&mut chip);
+    assert!(!chip.framebuffer().is_hires());
+    assert_eq!(chip.framebuffer().width(), 64);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn save_and_load_flags() {
+    let mut chip = chip(&[0xF275, 0xF285]);
+    chip.V[..3].copy_from_slice(&[7, 8, 9]);
+    step(&mut chip);
+    chip.V[..3].fill(0);
+    step(&mut chip);
+    assert_eq!(&chip.registers()[..3], &[7, 8, 9]);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn set_i_long() {
+    let mut chip = chip(&[0xF000, 0xBEEF]);
+    step(&mut chip);
+    assert_eq!(chip.index(), 0xBEEF);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn select_planes_draws_on_the_second_plane() {
+    let mut chip = chip_with(&[0xF201, 0xD011], Quirks::OCTO);
+    chip.I = FONT_START_ADDRESS as u16;
+    step(&mut chip);
+    step(&mut chip);
+    assert!(lit_pixels(&chip).iter().all(|&(_, _, value)| value == 2));
+    assert_eq!(lit_pixels(&chip).len(), 4);
+}
+
+#[test]
+fn load_audio_pattern_and_pitch() {
+    let mut chip = chip(&[0xF002, 0xF03A]);
+    assert_eq!(chip.audio_pattern(), None);
+    chip.I = 0x300;
+    chip.memory[0x300..0x310].fill(0xAA);
+    chip.V[0] = 100;
+    step(&mut chip);
+    step(&mut chip);
+
+    let pattern = chip.audio_pattern().unwrap();
+    assert_eq!(pattern.buffer, [0xAA; PATTERN_BYTES]);
+    assert_eq!(pattern.pitch, 100);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn save_and_load_register_ranges() {
+    let mut chip = chip(&[0x5132, 0x5313]);
+    chip.I = 0x300;
+    chip.V[1..4].copy_from_slice(&[1, 2, 3]);
+    step(&mut chip);
+    assert_eq!(&chip.memory()[0x300..0x303], &[1, 2, 3]);
+    assert_eq!(chip.index(), 0x300);
+
+    // Loading from V3 down to V1 reverses the order.
+    step(&mut chip);
+    assert_eq!(&chip.registers()[1..4], &[3, 2, 1]);
+    assert_eq!(chip.pc(), START + 4);
+}
+
+#[test]
+fn save_state_round_trips() {
+    let mut chip = chip(&[0x6005, 0xC0FF, 0xA300, 0xD015]);
+    for _ in 0..4 {
+        step(&mut chip);
+    }
+    let state = chip.save_state();
+
+    let mut restored = Chip8::new(Vec::new(), Quirks::OCTO).unwrap();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+}
+
+#[test]
+fn corrupt_save_state_is_rejected() {
+    let chip = chip(&[0x6005]);
+    let mut state = chip.save_state();
+    state[20] ^= 1;
+    let mut restored = Chip8::new(Vec::new(), Quirks::OCTO).unwrap();
+    assert!(matches!(
+        restored.load_state(&state),
+        Err(ChipErrors::InvalidSaveState(_))
+    ));
+}
+
+#[test]
+fn save_states_from_other_versions_are_rejected() {
+    let mut state = chip(&[0x6005]).save_state();
+    state.truncate(state.len() - 4);
+    state[8..10].copy_from_slice(&2u16.to_le_bytes());
+    let checksum = crate::hash::crc32(&state);
+    state.extend_from_slice(&checksum.to_le_bytes());
+
+    let mut restored = Chip8::new(Vec::new(), Quirks::OCTO).unwrap();
+    assert!(matches!(
+        restored.load_state(&state),
+        Err(ChipErrors::UnsupportedSaveStateVersion(2))
+    ));
+}
+
+#[test]
+fn default_quirks_keep_the_original_behaviour() {
+    // v0 := 3, v1 := 0xF0, v0 >>= v1, i := 0x300, save v0, jump0 0x20A
+    let mut chip = chip_with(
+        &[0x6003, 0x61F0, 0x8016, 0xA300, 0xF055, 0xB20A],
+        Quirks::default(),
+    );
+    for _ in 0..6 {
+        step(&mut chip);
+    }
+    assert_eq!(chip.registers()[0], 1, "shift ignores VY");
+    assert_eq!(chip.index(), 0x300, "save leaves I alone");
+    assert_eq!(chip.pc(), 0x20B, "jump0 adds V0");
+}
@@ -1,10 +1,41 @@
-use sdl2::keyboard::Keycode;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ChipErrors {
     #[error("Unknown opcode {0:04x}")]
     UnknownOpcode(u16),
+    #[error("Stack overflow at {pc:04x}")]
+    StackOverflow { pc: u16 },
+    #[error("Stack underflow at {pc:04x}")]
+    StackUnderflow { pc: u16 },
+    #[error("Memory access out of bounds at {addr:05x} (pc {pc:04x})")]
+    MemoryOutOfBounds { addr: usize, pc: u16 },
+    #[error("ROM is {size} bytes, but at most {max} fit in memory")]
+    RomTooLarge { size: usize, max: usize },
+    #[error("Invalid save state: {0}")]
+    InvalidSaveState(String),
+    #[error("Unsupported save state version {0}")]
+    UnsupportedSaveStateVersion(u16),
+}
+
+/// Problems with files and options the user supplies, rather than with the
+/// emulated machine.
+#[derive(Error, Debug)]
+pub enum ConfigError {
     #[error("Unknown key code {0}")]
-    UnknownKeycode(Keycode),
+    UnknownKeycode(String),
+    #[error("Unknown quirk preset {0}")]
+    UnknownQuirkPreset(String),
+    #[error("Invalid quirk override {0}")]
+    InvalidQuirk(String),
+    #[error("Invalid key press {0}, expected KEY@FRAME[+FRAMES]")]
+    InvalidKeyPress(String),
+    #[error("Invalid movie: {0}")]
+    InvalidMovie(String),
+    #[error("Movie was recorded with ROM {expected:016x}, but this ROM is {found:016x}")]
+    MovieRomMismatch { expected: u64, found: u64 },
+    #[error("Invalid key bindings: {0}")]
+    InvalidBindings(String),
+    #[error("Invalid palette {0}, expected a preset name or 2 or 4 #RRGGBB colours")]
+    InvalidPalette(String),
 }
@@ -0,0 +1,131 @@
+pub const CHIP8_WIDTH: usize = 64;
+pub const CHIP8_HEIGHT: usize = 32;
+pub const SCHIP_WIDTH: usize = 128;
+pub const SCHIP_HEIGHT: usize = 64;
+
+/// Screen whose size follows the low/high resolution mode. Each pixel holds
+/// one bit per XO-CHIP plane, so values range over 0..=3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Rebuilds a screen from raw pixels, as produced by `pixels()`. Returns
+    /// `None` unless the size is one of the two modes and every pixel is valid.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<u8>) -> Option<Self> {
+        let valid_size = (width, height) == (CHIP8_WIDTH, CHIP8_HEIGHT)
+            || (width, height) == (SCHIP_WIDTH, SCHIP_HEIGHT);
+        if !valid_size || pixels.len() != width * height || pixels.iter().any(|&pixel| pixel > 3) {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.width == SCHIP_WIDTH
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// All pixels in row-major order.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks(self.width)
+    }
+
+    /// Clears the bit planes selected by `planes`.
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    /// Switches between 64x32 and 128x64. The screen is cleared on every switch.
+    pub fn set_hires(&mut self, hires: bool) {
+        *self = if hires {
+            Self::new(SCHIP_WIDTH, SCHIP_HEIGHT)
+        } else {
+            Self::new(CHIP8_WIDTH, CHIP8_HEIGHT)
+        };
+    }
+
+    /// XORs a lit pixel onto one bit plane and reports whether it erased one.
+    pub fn toggle(&mut self, x: usize, y: usize, plane: u8) -> bool {
+        let pixel = &mut self.pixels[y * self.width + x];
+        let collision = *pixel & plane != 0;
+        *pixel ^= plane;
+        collision
+    }
+
+    pub fn scroll_down(&mut self, rows: usize, planes: u8) {
+        self.shift(0, rows as isize, planes);
+    }
+
+    pub fn scroll_up(&mut self, rows: usize, planes: u8) {
+        self.shift(0, -(rows as isize), planes);
+    }
+
+    pub fn scroll_left(&mut self, columns: usize, planes: u8) {
+        self.shift(-(columns as isize), 0, planes);
+    }
+
+    pub fn scroll_right(&mut self, columns: usize, planes: u8) {
+        self.shift(columns as isize, 0, planes);
+    }
+
+    /// Moves the selected bit planes by (dx, dy), filling uncovered pixels with zero.
+    fn shift(&mut self, dx: isize, dy: isize, planes: u8) {
+        let source = self.pixels.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let from_x = x as isize - dx;
+                let from_y = y as isize - dy;
+                let moved = if (0..self.width as isize).contains(&from_x)
+                    && (0..self.height as isize).contains(&from_y)
+                {
+                    source[from_y as usize * self.width + from_x as usize] & planes
+                } else {
+                    0
+                };
+
+                let pixel = &mut self.pixels[y * self.width + x];
+                *pixel = (*pixel & !planes) | moved;
+            }
+        }
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new(CHIP8_WIDTH, CHIP8_HEIGHT)
+    }
+}
@@ -1,3 +1,7 @@
+pub mod audio;
 pub mod chip8;
 pub mod errors;
-mod opcode;
+pub mod framebuffer;
+pub mod opcode;
+pub mod quirks;
+pub mod random;
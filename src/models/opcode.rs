@@ -1,5 +1,8 @@
+use std::fmt;
+
 use crate::models::errors::ChipErrors;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     SetI(u16),
     SetVConstant(u8, u8),
@@ -10,8 +13,8 @@ pub enum Opcode {
     Add(u8, u8),
     Subtract(u8, u8),
     SubtractOpposite(u8, u8),
-    ShiftLeft(u8),
-    ShiftRight(u8),
+    ShiftLeft(u8, u8),
+    ShiftRight(u8, u8),
     Or(u8, u8),
     And(u8, u8),
     Xor(u8, u8),
@@ -28,12 +31,29 @@ pub enum Opcode {
     JumpPlus(u16),
     SetDelayTimer(u8),
     GetDelayTimer(u8),
+    SetSoundTimer(u8),
     Dump(u8),
     Load(u8),
     SpriteAddress(u8),
     RandAnd(u8, u8),
     AddMemory(u8),
     GetKey(u8),
+    ScrollDown(u8),
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowResolution,
+    HighResolution,
+    BigSpriteAddress(u8),
+    SaveFlags(u8),
+    LoadFlags(u8),
+    ScrollUp(u8),
+    SetILong,
+    SelectPlanes(u8),
+    LoadAudioPattern,
+    SetPitch(u8),
+    SaveRange(u8, u8),
+    LoadRange(u8, u8),
 }
 
 impl Opcode {
@@ -44,6 +64,22 @@ impl Opcode {
             return Ok(Self::ClearScreen);
         } else if code == 0x00EE {
             return Ok(Self::ReturnFromSubroutine);
+        } else if code & 0xFFF0 == 0x00C0 {
+            let n = (code & 0x000F) as u8;
+            return Ok(Self::ScrollDown(n));
+        } else if code & 0xFFF0 == 0x00D0 {
+            let n = (code & 0x000F) as u8;
+            return Ok(Self::ScrollUp(n));
+        } else if code == 0x00FB {
+            return Ok(Self::ScrollRight);
+        } else if code == 0x00FC {
+            return Ok(Self::ScrollLeft);
+        } else if code == 0x00FD {
+            return Ok(Self::Exit);
+        } else if code == 0x00FE {
+            return Ok(Self::LowResolution);
+        } else if code == 0x00FF {
+            return Ok(Self::HighResolution);
         } else if code & 0xF000 == 0x2000 {
             return Ok(Self::CallSubroutine(code & 0x0FFF));
         } else if code & 0xF00F == 0x8004 {
@@ -84,10 +120,12 @@ impl Opcode {
             return Ok(Self::Xor(x, y));
         } else if code & 0xF00F == 0x800E {
             let x = ((code & 0x0F00) >> 8) as u8;
-            return Ok(Self::ShiftLeft(x));
+            let y = ((code & 0x00F0) >> 4) as u8;
+            return Ok(Self::ShiftLeft(x, y));
         } else if code & 0xF00F == 0x8006 {
             let x = ((code & 0x0F00) >> 8) as u8;
-            return Ok(Self::ShiftRight(x));
+            let y = ((code & 0x00F0) >> 4) as u8;
+            return Ok(Self::ShiftRight(x, y));
         } else if code & 0xF00F == 0x8005 {
             let x = ((code & 0x0F00) >> 8) as u8;
             let y = ((code & 0x00F0) >> 4) as u8;
@@ -117,6 +155,9 @@ impl Opcode {
         } else if code & 0xF0FF == 0xF015 {
             let x = ((code & 0x0F00) >> 8) as u8;
             return Ok(Self::SetDelayTimer(x));
+        } else if code & 0xF0FF == 0xF018 {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            return Ok(Self::SetSoundTimer(x));
         } else if code & 0xF0FF == 0xF055 {
             let x = ((code & 0x0F00) >> 8) as u8;
             return Ok(Self::Dump(x));
@@ -126,6 +167,15 @@ impl Opcode {
         } else if code & 0xF0FF == 0xF029 {
             let x = ((code & 0x0F00) >> 8) as u8;
             return Ok(Self::SpriteAddress(x));
+        } else if code & 0xF0FF == 0xF030 {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            return Ok(Self::BigSpriteAddress(x));
+        } else if code & 0xF0FF == 0xF075 {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            return Ok(Self::SaveFlags(x));
+        } else if code & 0xF0FF == 0xF085 {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            return Ok(Self::LoadFlags(x));
         } else if code & 0xF0FF == 0xF007 {
             let x = ((code & 0x0F00) >> 8) as u8;
             return Ok(Self::GetDelayTimer(x));
@@ -141,6 +191,24 @@ impl Opcode {
         } else if code & 0xF0FF == 0xE0A1 {
             let x = ((code & 0x0F00) >> 8) as u8;
             return Ok(Self::SkipKeyNonEqual(x));
+        } else if code == 0xF000 {
+            return Ok(Self::SetILong);
+        } else if code & 0xF0FF == 0xF001 {
+            let n = ((code & 0x0F00) >> 8) as u8;
+            return Ok(Self::SelectPlanes(n));
+        } else if code == 0xF002 {
+            return Ok(Self::LoadAudioPattern);
+        } else if code & 0xF0FF == 0xF03A {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            return Ok(Self::SetPitch(x));
+        } else if code & 0xF00F == 0x5002 {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            let y = ((code & 0x00F0) >> 4) as u8;
+            return Ok(Self::SaveRange(x, y));
+        } else if code & 0xF00F == 0x5003 {
+            let x = ((code & 0x0F00) >> 8) as u8;
+            let y = ((code & 0x00F0) >> 4) as u8;
+            return Ok(Self::LoadRange(x, y));
         } else if code & 0xF00F == 0x5000 {
             let x = ((code & 0x0F00) >> 8) as u8;
             let y = ((code & 0x00F0) >> 4) as u8;
@@ -154,3 +222,145 @@ impl Opcode {
         }
     }
 }
+
+impl Opcode {
+    /// Inverse of `parse`. `SetILong` encodes only its first word; the
+    /// 16-bit address follows it in memory.
+    pub fn encode(&self) -> u16 {
+        let xy = |base: u16, x: u8, y: u8| base | (x as u16) << 8 | (y as u16) << 4;
+        let xn = |base: u16, x: u8, n: u8| base | (x as u16) << 8 | n as u16;
+        let x_ = |base: u16, x: u8| base | (x as u16) << 8;
+
+        match *self {
+            Self::SetI(addr) => 0xA000 | addr,
+            Self::SetVConstant(x, n) => xn(0x6000, x, n),
+            Self::SetV(x, y) => xy(0x8000, x, y),
+            Self::ClearScreen => 0x00E0,
+            Self::ReturnFromSubroutine => 0x00EE,
+            Self::CallSubroutine(addr) => 0x2000 | addr,
+            Self::Add(x, y) => xy(0x8004, x, y),
+            Self::Subtract(x, y) => xy(0x8005, x, y),
+            Self::SubtractOpposite(x, y) => xy(0x8007, x, y),
+            Self::ShiftLeft(x, y) => xy(0x800E, x, y),
+            Self::ShiftRight(x, y) => xy(0x8006, x, y),
+            Self::Or(x, y) => xy(0x8001, x, y),
+            Self::And(x, y) => xy(0x8002, x, y),
+            Self::Xor(x, y) => xy(0x8003, x, y),
+            Self::AddConstant(x, n) => xn(0x7000, x, n),
+            Self::BinaryCodedDecimal(x) => x_(0xF033, x),
+            Self::SkipRegistersEqual(x, y) => xy(0x5000, x, y),
+            Self::SkipRegistersNonEqual(x, y) => xy(0x9000, x, y),
+            Self::SkipEqual(x, n) => xn(0x3000, x, n),
+            Self::SkipNonEqual(x, n) => xn(0x4000, x, n),
+            Self::SkipKeyEqual(x) => x_(0xE09E, x),
+            Self::SkipKeyNonEqual(x) => x_(0xE0A1, x),
+            Self::Draw(x, y, n) => xy(0xD000, x, y) | n as u16,
+            Self::Jump(addr) => 0x1000 | addr,
+            Self::JumpPlus(addr) => 0xB000 | addr,
+            Self::SetDelayTimer(x) => x_(0xF015, x),
+            Self::GetDelayTimer(x) => x_(0xF007, x),
+            Self::SetSoundTimer(x) => x_(0xF018, x),
+            Self::Dump(x) => x_(0xF055, x),
+            Self::Load(x) => x_(0xF065, x),
+            Self::SpriteAddress(x) => x_(0xF029, x),
+            Self::RandAnd(x, n) => xn(0xC000, x, n),
+            Self::AddMemory(x) => x_(0xF01E, x),
+            Self::GetKey(x) => x_(0xF00A, x),
+            Self::ScrollDown(n) => 0x00C0 | n as u16,
+            Self::ScrollRight => 0x00FB,
+            Self::ScrollLeft => 0x00FC,
+            Self::Exit => 0x00FD,
+            Self::LowResolution => 0x00FE,
+            Self::HighResolution => 0x00FF,
+            Self::BigSpriteAddress(x) => x_(0xF030, x),
+            Self::SaveFlags(x) => x_(0xF075, x),
+            Self::LoadFlags(x) => x_(0xF085, x),
+            Self::ScrollUp(n) => 0x00D0 | n as u16,
+            Self::SetILong => 0xF000,
+            Self::SelectPlanes(n) => x_(0xF001, n),
+            Self::LoadAudioPattern => 0xF002,
+            Self::SetPitch(x) => x_(0xF03A, x),
+            Self::SaveRange(x, y) => xy(0x5002, x, y),
+            Self::LoadRange(x, y) => xy(0x5003, x, y),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::SetI(addr) => write!(f, "LD I, 0x{addr:03X}"),
+            Self::SetVConstant(x, n) => write!(f, "LD V{x:X}, 0x{n:02X}"),
+            Self::SetV(x, y) => write!(f, "LD V{x:X}, V{y:X}"),
+            Self::ClearScreen => write!(f, "CLS"),
+            Self::ReturnFromSubroutine => write!(f, "RET"),
+            Self::CallSubroutine(addr) => write!(f, "CALL 0x{addr:03X}"),
+            Self::Add(x, y) => write!(f, "ADD V{x:X}, V{y:X}"),
+            Self::Subtract(x, y) => write!(f, "SUB V{x:X}, V{y:X}"),
+            Self::SubtractOpposite(x, y) => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Self::ShiftLeft(x, y) => write!(f, "SHL V{x:X}, V{y:X}"),
+            Self::ShiftRight(x, y) => write!(f, "SHR V{x:X}, V{y:X}"),
+            Self::Or(x, y) => write!(f, "OR V{x:X}, V{y:X}"),
+            Self::And(x, y) => write!(f, "AND V{x:X}, V{y:X}"),
+            Self::Xor(x, y) => write!(f, "XOR V{x:X}, V{y:X}"),
+            Self::AddConstant(x, n) => write!(f, "ADD V{x:X}, 0x{n:02X}"),
+            Self::BinaryCodedDecimal(x) => write!(f, "LD B, V{x:X}"),
+            Self::SkipRegistersEqual(x, y) => write!(f, "SE V{x:X}, V{y:X}"),
+            Self::SkipRegistersNonEqual(x, y) => write!(f, "SNE V{x:X}, V{y:X}"),
+            Self::SkipEqual(x, n) => write!(f, "SE V{x:X}, 0x{n:02X}"),
+            Self::SkipNonEqual(x, n) => write!(f, "SNE V{x:X}, 0x{n:02X}"),
+            Self::SkipKeyEqual(x) => write!(f, "SKP V{x:X}"),
+            Self::SkipKeyNonEqual(x) => write!(f, "SKNP V{x:X}"),
+            Self::Draw(x, y, n) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Self::Jump(addr) => write!(f, "JP 0x{addr:03X}"),
+            Self::JumpPlus(addr) => write!(f, "JP V0, 0x{addr:03X}"),
+            Self::SetDelayTimer(x) => write!(f, "LD DT, V{x:X}"),
+            Self::GetDelayTimer(x) => write!(f, "LD V{x:X}, DT"),
+            Self::SetSoundTimer(x) => write!(f, "LD ST, V{x:X}"),
+            Self::Dump(x) => write!(f, "LD [I], V{x:X}"),
+            Self::Load(x) => write!(f, "LD V{x:X}, [I]"),
+            Self::SpriteAddress(x) => write!(f, "LD F, V{x:X}"),
+            Self::RandAnd(x, n) => write!(f, "RND V{x:X}, 0x{n:02X}"),
+            Self::AddMemory(x) => write!(f, "ADD I, V{x:X}"),
+            Self::GetKey(x) => write!(f, "LD V{x:X}, K"),
+            Self::ScrollDown(n) => write!(f, "SCD {n}"),
+            Self::ScrollRight => write!(f, "SCR"),
+            Self::ScrollLeft => write!(f, "SCL"),
+            Self::Exit => write!(f, "EXIT"),
+            Self::LowResolution => write!(f, "LOW"),
+            Self::HighResolution => write!(f, "HIGH"),
+            Self::BigSpriteAddress(x) => write!(f, "LD HF, V{x:X}"),
+            Self::SaveFlags(x) => write!(f, "LD R, V{x:X}"),
+            Self::LoadFlags(x) => write!(f, "LD V{x:X}, R"),
+            Self::ScrollUp(n) => write!(f, "SCU {n}"),
+            Self::SetILong => write!(f, "LD I, LONG"),
+            Self::SelectPlanes(n) => write!(f, "PLANE {n}"),
+            Self::LoadAudioPattern => write!(f, "AUDIO"),
+            Self::SetPitch(x) => write!(f, "PITCH V{x:X}"),
+            Self::SaveRange(x, y) => write!(f, "SAVE V{x:X} - V{y:X}"),
+            Self::LoadRange(x, y) => write!(f, "LOAD V{x:X} - V{y:X}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_inverts_parse() {
+        for word in 0..=u16::MAX {
+            if let Ok(opcode) = Opcode::parse(word) {
+                assert_eq!(opcode.encode(), word, "{opcode:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_opcode_is_an_error() {
+        assert!(matches!(
+            Opcode::parse(0xE000),
+            Err(ChipErrors::UnknownOpcode(0xE000))
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use std::str::FromStr;
+
+use super::errors::ConfigError;
+
+/// How FX55/FX65 leave the I register after a register dump or load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    /// I is left untouched (SUPER-CHIP).
+    Unchanged,
+    /// I is advanced by X (CHIP-48).
+    X,
+    /// I is advanced by X + 1 (COSMAC VIP, XO-CHIP).
+    XPlusOne,
+}
+
+/// Behavioural differences between historical CHIP-8 interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY1/8XY2/8XY3 reset VF to zero.
+    pub vf_reset: bool,
+    /// Effect of FX55/FX65 on I.
+    pub memory: IndexIncrement,
+    /// DXYN waits for the next frame before executing further instructions.
+    pub display_wait: bool,
+    /// Sprites are clipped at the screen edge instead of wrapping around.
+    pub clipping: bool,
+    /// 8XY6/8XYE shift VX in place and ignore VY.
+    pub shifting: bool,
+    /// BNNN jumps to NNN + VX, where X is the high nibble of NNN, instead of NNN + V0.
+    pub jumping: bool,
+}
+
+impl Quirks {
+    /// How this emulator behaved before quirks were configurable: shifts
+    /// ignore VY, FX55/FX65 leave I alone, BNNN adds V0, sprites wrap and
+    /// nothing waits for the display. The default, so existing setups keep
+    /// working.
+    pub const LEGACY: Quirks = Quirks {
+        vf_reset: false,
+        memory: IndexIncrement::Unchanged,
+        display_wait: false,
+        clipping: false,
+        shifting: true,
+        jumping: false,
+    };
+
+    pub const COSMAC_VIP: Quirks = Quirks {
+        vf_reset: true,
+        memory: IndexIncrement::XPlusOne,
+        display_wait: true,
+        clipping: true,
+        shifting: false,
+        jumping: false,
+    };
+
+    pub const CHIP_48: Quirks = Quirks {
+        vf_reset: false,
+        memory: IndexIncrement::X,
+        display_wait: false,
+        clipping: true,
+        shifting: true,
+        jumping: true,
+    };
+
+    pub const SUPER_CHIP: Quirks = Quirks {
+        vf_reset: false,
+        memory: IndexIncrement::Unchanged,
+        display_wait: false,
+        clipping: true,
+        shifting: true,
+        jumping: true,
+    };
+
+    pub const OCTO: Quirks = Quirks {
+        vf_reset: false,
+        memory: IndexIncrement::XPlusOne,
+        display_wait: false,
+        clipping: false,
+        shifting: false,
+        jumping: false,
+    };
+
+    /// Packs the quirks into bytes for save states and movies.
+    pub fn to_bytes(&self) -> [u8; 6] {
+        [
+            self.vf_reset as u8,
+            match self.memory {
+                IndexIncrement::Unchanged => 0,
+                IndexIncrement::X => 1,
+                IndexIncrement::XPlusOne => 2,
+            },
+            self.display_wait as u8,
+            self.clipping as u8,
+            self.shifting as u8,
+            self.jumping as u8,
+        ]
+    }
+
+    /// Inverse of `to_bytes`. Returns `None` on out-of-range values.
+    pub fn from_bytes(bytes: [u8; 6]) -> Option<Self> {
+        let flag = |byte: u8| match byte {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        };
+
+        Some(Self {
+            vf_reset: flag(bytes[0])?,
+            memory: match bytes[1] {
+                0 => IndexIncrement::Unchanged,
+                1 => IndexIncrement::X,
+                2 => IndexIncrement::XPlusOne,
+                _ => return None,
+            },
+            display_wait: flag(bytes[2])?,
+            clipping: flag(bytes[3])?,
+            shifting: flag(bytes[4])?,
+            jumping: flag(bytes[5])?,
+        })
+    }
+
+    /// Applies a single `name=value` override, e.g. `clipping=off` or `memory=x+1`.
+    pub fn apply_override(&mut self, spec: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidQuirk(spec.to_string());
+        let (name, value) = spec.split_once('=').ok_or_else(invalid)?;
+
+        if name == "memory" {
+            self.memory = match value {
+                "unchanged" => IndexIncrement::Unchanged,
+                "x" => IndexIncrement::X,
+                "x+1" => IndexIncrement::XPlusOne,
+                _ => return Err(invalid()),
+            };
+            return Ok(());
+        }
+
+        let enabled = match value {
+            "on" | "true" | "1" => true,
+            "off" | "false" | "0" => false,
+            _ => return Err(invalid()),
+        };
+
+        match name {
+            "vf-reset" => self.vf_reset = enabled,
+            "display-wait" => self.display_wait = enabled,
+            "clipping" => self.clipping = enabled,
+            "shifting" => self.shifting = enabled,
+            "jumping" => self.jumping = enabled,
+            _ => return Err(invalid()),
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::LEGACY
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "legacy" => Ok(Self::LEGACY),
+            "vip" | "cosmac-vip" | "chip-8" | "chip8" => Ok(Self::COSMAC_VIP),
+            "chip-48" | "chip48" => Ok(Self::CHIP_48),
+            "schip" | "super-chip" | "superchip" => Ok(Self::SUPER_CHIP),
+            "octo" | "xo-chip" | "xochip" => Ok(Self::OCTO),
+            _ => Err(ConfigError::UnknownQuirkPreset(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+/// Seedable source of CXNN random bytes (SplitMix64).
+///
+/// The whole generator is two words, so it can be stored in save states and
+/// replays: two machines with the same seed and inputs behave identically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Random {
+    seed: u64,
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Seeds from OS entropy, for runs that need not be reproducible.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    /// Rebuilds a generator mid-sequence, as recorded by `seed()` and `state()`.
+    pub fn restore(seed: u64, state: u64) -> Self {
+        Self { seed, state }
+    }
+
+    /// The seed this generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
@@ -0,0 +1,176 @@
+//! Input movies: the keypad state of every frame since power-on.
+//!
+//! Replaying a movie with the same ROM, seed, quirks and speed reproduces
+//! the recorded run exactly. Layout, all integers little-endian:
+//!
+//! | field             | size          |
+//! |-------------------|---------------|
+//! | magic `CH8MOVIE`  | 8             |
+//! | version           | 2             |
+//! | ROM hash          | 8             |
+//! | RNG seed          | 8             |
+//! | quirks            | 6             |
+//! | cycles per frame  | 4             |
+//! | frame count       | 4             |
+//! | keypad masks      | 2 per frame   |
+//! | final state hash  | 8             |
+
+use anyhow::Result;
+
+use crate::{
+    bytes::Reader,
+    hash::fnv1a,
+    input::keyboard::Keyboard,
+    models::{chip8::Chip8, errors::ConfigError, quirks::Quirks},
+};
+
+const MAGIC: &[u8; 8] = b"CH8MOVIE";
+pub const MOVIE_VERSION: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub cycles_per_frame: u32,
+    /// Keypad bitmask for each frame, see `Keyboard::mask`.
+    pub frames: Vec<u16>,
+    /// Fingerprint of the machine after the last frame, see `state_hash`.
+    pub final_state: u64,
+}
+
+impl Movie {
+    /// Starts an empty recording for `chip`, which must be freshly created.
+    pub fn new(rom: &[u8], chip: &Chip8, cycles_per_frame: u32) -> Self {
+        Self {
+            rom_hash: fnv1a(rom),
+            seed: chip.random().seed(),
+            quirks: *chip.quirks(),
+            cycles_per_frame,
+            frames: Vec::new(),
+            final_state: 0,
+        }
+    }
+
+    /// Creates the machine the movie was recorded on, failing with
+    /// `ConfigError::MovieRomMismatch` if `rom` is not the recorded ROM.
+    pub fn start(&self, rom: Vec<u8>) -> Result<Chip8> {
+        let found = fnv1a(&rom);
+        if found != self.rom_hash {
+            return Err(ConfigError::MovieRomMismatch {
+                expected: self.rom_hash,
+                found,
+            }
+            .into());
+        }
+
+        Ok(Chip8::new(rom, self.quirks)?.with_seed(self.seed))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(48 + self.frames.len() * 2);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&MOVIE_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.rom_hash.to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&self.quirks.to_bytes());
+        out.extend_from_slice(&self.cycles_per_frame.to_le_bytes());
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for mask in &self.frames {
+            out.extend_from_slice(&mask.to_le_bytes());
+        }
+        out.extend_from_slice(&self.final_state.to_le_bytes());
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ConfigError> {
+        let mut reader = Reader::new(data, ConfigError::InvalidMovie);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a movie"));
+        }
+        let version = reader.u16()?;
+        if version != MOVIE_VERSION {
+            return Err(invalid(&format!("unsupported version {version}")));
+        }
+
+        let rom_hash = reader.u64()?;
+        let seed = reader.u64()?;
+        let quirks = Quirks::from_bytes(reader.array()?).ok_or_else(|| invalid("bad quirks"))?;
+        let cycles_per_frame = reader.u32()?;
+        let count = reader.u32()?;
+        let frames = (0..count)
+            .map(|_| reader.u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let final_state = reader.u64()?;
+
+        if !reader.is_empty() {
+            return Err(invalid("trailing data"));
+        }
+
+        Ok(Self {
+            rom_hash,
+            seed,
+            quirks,
+            cycles_per_frame,
+            frames,
+            final_state,
+        })
+    }
+
+    /// Appends one frame of input.
+    pub fn record(&mut self, keyboard: &Keyboard) {
+        self.frames.push(keyboard.mask());
+    }
+
+    /// Drops the last `frames` frames, e.g. after rewinding.
+    pub fn truncate(&mut self, frames: usize) {
+        self.frames
+            .truncate(self.frames.len().saturating_sub(frames));
+    }
+
+    /// Seals the recording with the fingerprint of the machine's final state.
+    pub fn finish(&mut self, chip: &Chip8) {
+        self.final_state = state_hash(chip);
+    }
+}
+
+/// Replays a movie one frame at a time.
+pub struct Playback {
+    movie: Movie,
+    position: usize,
+}
+
+impl Playback {
+    pub fn new(movie: Movie) -> Self {
+        Self { movie, position: 0 }
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    /// The keypad for the next frame, or `None` once the movie is over.
+    pub fn next_frame(&mut self) -> Option<Keyboard> {
+        let mask = *self.movie.frames.get(self.position)?;
+        self.position += 1;
+        Some(Keyboard::from_mask(mask))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.movie.frames.len()
+    }
+
+    /// Whether `chip`, after the last frame, ended where the recording did.
+    pub fn in_sync(&self, chip: &Chip8) -> bool {
+        state_hash(chip) == self.movie.final_state
+    }
+}
+
+/// Fingerprint of the complete machine state.
+pub fn state_hash(chip: &Chip8) -> u64 {
+    fnv1a(&chip.save_state())
+}
+
+fn invalid(reason: &str) -> ConfigError {
+    ConfigError::InvalidMovie(reason.to_string())
+}
@@ -0,0 +1,166 @@
+//! Screen colours. A palette maps each pixel value to a colour: 0 is the
+//! background, 1 and 2 are the first and second XO-CHIP planes, and 3 is a
+//! pixel set in both.
+
+use std::{fmt, str::FromStr};
+
+use crate::models::errors::ConfigError;
+
+pub type Rgb = [u8; 3];
+
+/// Built-in palettes, in the order the palette hotkey cycles through them.
+pub const PRESETS: [(&str, [Rgb; 4]); 7] = [
+    (
+        "default",
+        [[0, 0, 255], [0, 255, 0], [255, 0, 0], [255, 255, 0]],
+    ),
+    (
+        "classic",
+        [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]],
+    ),
+    (
+        "amber",
+        [[26, 15, 0], [255, 176, 0], [179, 107, 0], [255, 221, 140]],
+    ),
+    (
+        "green",
+        [[0, 20, 0], [51, 255, 51], [26, 140, 26], [190, 255, 190]],
+    ),
+    (
+        "lcd",
+        [[196, 207, 161], [31, 31, 31], [139, 149, 109], [77, 83, 60]],
+    ),
+    (
+        "high-contrast",
+        [[0, 0, 0], [255, 255, 255], [255, 255, 0], [0, 255, 255]],
+    ),
+    // Okabe-Ito colours, distinguishable with any common colour blindness.
+    (
+        "colour-blind",
+        [[0, 0, 0], [230, 159, 0], [86, 180, 233], [240, 228, 66]],
+    ),
+];
+
+/// A named set of four colours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub name: String,
+    pub colours: [Rgb; 4],
+}
+
+impl Palette {
+    /// Every built-in palette, in cycling order.
+    pub fn presets() -> Vec<Self> {
+        PRESETS
+            .iter()
+            .map(|(name, colours)| Self {
+                name: name.to_string(),
+                colours: *colours,
+            })
+            .collect()
+    }
+
+    /// A palette for single-plane programs: pixels of the first plane are
+    /// `on`, and second-plane pixels are blended a third and two thirds of
+    /// the way from `off` towards `on`.
+    pub fn two_colour(name: impl Into<String>, off: Rgb, on: Rgb) -> Self {
+        let blend = |thirds: u16| {
+            std::array::from_fn(|channel| {
+                ((off[channel] as u16 * (3 - thirds) + on[channel] as u16 * thirds) / 3) as u8
+            })
+        };
+        Self {
+            name: name.into(),
+            colours: [off, on, blend(1), blend(2)],
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let (name, colours) = PRESETS[0];
+        Self {
+            name: name.to_string(),
+            colours,
+        }
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Parses a preset name, or two or four comma-separated `#RRGGBB` colours.
+impl FromStr for Palette {
+    type Err = ConfigError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let name = spec.trim().to_ascii_lowercase();
+        if let Some((_, colours)) = PRESETS.iter().find(|(preset, _)| *preset == name) {
+            return Ok(Self {
+                name,
+                colours: *colours,
+            });
+        }
+
+        let colours = name
+            .split(',')
+            .map(parse_rgb)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ConfigError::InvalidPalette(spec.to_string()))?;
+        match colours[..] {
+            [off, on] => Ok(Self::two_colour(name, off, on)),
+            [off, first, second, both] => Ok(Self {
+                name,
+                colours: [off, first, second, both],
+            }),
+            _ => Err(ConfigError::InvalidPalette(spec.to_string())),
+        }
+    }
+}
+
+/// Parses `#RRGGBB`, with the `#` optional.
+fn parse_rgb(hex: &str) -> Option<Rgb> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [_, r, g, b] = value.to_be_bytes();
+    Some([r, g, b])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_presets_and_hex_colours() {
+        assert_eq!("Amber".parse::<Palette>().unwrap().colours, PRESETS[2].1);
+
+        let custom: Palette = "#102030, 405060, #708090, #a0b0c0".parse().unwrap();
+        assert_eq!(
+            custom.colours,
+            [[16, 32, 48], [64, 80, 96], [112, 128, 144], [160, 176, 192]]
+        );
+
+        let two: Palette = "#000000,#ff9930".parse().unwrap();
+        assert_eq!(
+            two.colours,
+            [[0, 0, 0], [255, 153, 48], [85, 51, 16], [170, 102, 32]]
+        );
+
+        for bad in [
+            "sepia",
+            "#fff,#000",
+            "#000000",
+            "#000000,#ffffff,#ff0000",
+            "#00000g,#ffffff",
+        ] {
+            assert!(bad.parse::<Palette>().is_err(), "{bad}");
+        }
+    }
+}
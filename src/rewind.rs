@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use crate::models::{chip8::Chip8, errors::ChipErrors};
+
+/// Zero bytes tolerated inside a literal run before it is split in two.
+/// Shorter gaps cost more to skip than to copy.
+const MIN_GAP: usize = 4;
+
+/// Ring buffer of recent machine states for playing a game backwards.
+///
+/// Only the newest snapshot is kept in full. Older ones are stored as
+/// reverse deltas (newer XOR older, run-length encoded), which are tiny
+/// because memory barely changes between frames. When the buffer grows
+/// past `budget` bytes, the oldest deltas are dropped.
+pub struct Rewind {
+    budget: usize,
+    interval: u32,
+    frames: u32,
+    current: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    used: usize,
+}
+
+impl Rewind {
+    /// Keeps up to `budget` bytes of history, taking a snapshot every frame.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            interval: 1,
+            frames: 0,
+            current: None,
+            deltas: VecDeque::new(),
+            used: 0,
+        }
+    }
+
+    /// Takes a snapshot every `frames` frames instead. Each rewind step then
+    /// goes back that far.
+    pub fn with_interval(mut self, frames: u32) -> Self {
+        self.interval = frames.max(1);
+        self
+    }
+
+    /// Number of steps that can currently be rewound.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Bytes of history currently held.
+    pub fn memory_used(&self) -> usize {
+        self.used
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.deltas.clear();
+        self.used = 0;
+        self.frames = 0;
+    }
+
+    /// Called once per emulated frame. Snapshots `chip` every `interval` frames.
+    pub fn record(&mut self, chip: &Chip8) {
+        self.frames += 1;
+        if self.frames < self.interval && self.current.is_some() {
+            return;
+        }
+        self.frames = 0;
+
+        let state = chip.save_state();
+        if let Some(previous) = self.current.take() {
+            let delta = encode_delta(&state, &previous);
+            self.used += delta.len();
+            self.used -= previous.len();
+            self.deltas.push_back(delta);
+        }
+        self.used += state.len();
+        self.current = Some(state);
+
+        while self.used > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.used -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Goes back to the previous snapshot and returns how many frames that
+    /// undid. Returns `Ok(0)` once the history is exhausted, leaving `chip`
+    /// at the oldest snapshot.
+    pub fn step_back(&mut self, chip: &mut Chip8) -> Result<u32, ChipErrors> {
+        let Some(current) = self.current.take() else {
+            return Ok(0);
+        };
+
+        // Frames run since the newest snapshot are undone first.
+        if self.frames > 0 || self.deltas.is_empty() {
+            chip.load_state(&current)?;
+            self.current = Some(current);
+            return Ok(std::mem::take(&mut self.frames));
+        }
+
+        let delta = self.deltas.pop_back().expect("checked above");
+        let previous = apply_delta(&current, &delta);
+        chip.load_state(&previous)?;
+        self.used -= current.len() + delta.len();
+        self.used += previous.len();
+        self.current = Some(previous);
+
+        Ok(self.interval)
+    }
+}
+
+/// Encodes the changes turning `from` into `to` as a target length followed
+/// by `(skip, length, xor bytes)` runs, all lengths as LEB128 varints.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let xor = |i: usize| from.get(i).copied().unwrap_or(0) ^ to[i];
+
+    let mut delta = Vec::new();
+    put_varint(&mut delta, to.len());
+
+    let mut last = 0;
+    let mut i = 0;
+    while i < to.len() {
+        if xor(i) == 0 {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i;
+        let mut zeros = 0;
+        while i < to.len() && zeros < MIN_GAP {
+            if xor(i) == 0 {
+                zeros += 1;
+            } else {
+                zeros = 0;
+                end = i + 1;
+            }
+            i += 1;
+        }
+
+        put_varint(&mut delta, start - last);
+        put_varint(&mut delta, end - start);
+        delta.extend((start..end).map(xor));
+        last = end;
+        i = end;
+    }
+
+    delta
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut delta = delta.iter().copied();
+    let len = get_varint(&mut delta);
+
+    let mut out = base.to_vec();
+    out.resize(len, 0);
+
+    let mut position = 0;
+    while let Some(skip) = try_get_varint(&mut delta) {
+        position += skip;
+        let run = get_varint(&mut delta);
+        for byte in &mut out[position..position + run] {
+            *byte ^= delta.next().unwrap_or_default();
+        }
+        position += run;
+    }
+
+    out
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn try_get_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    for byte in bytes {
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+    None
+}
+
+fn get_varint(bytes: &mut impl Iterator<Item = u8>) -> usize {
+    try_get_varint(bytes).unwrap_or_default()
+}
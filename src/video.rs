@@ -0,0 +1,477 @@
+//! Gameplay recording to animated GIF or raw Y4M video, without any codec
+//! library. Both take one frame per 1/60 s tick.
+
+use std::io::{self, Write};
+
+use crate::models::{chip8::TIMER_FREQUENCY, framebuffer::Framebuffer};
+
+/// Shortest GIF frame delay in centiseconds. Browsers show anything shorter
+/// at 1/10 s, so faster changes drop frames instead.
+const MIN_GIF_DELAY: u64 = 2;
+/// Bits per pixel of the four-colour GIF images.
+const GIF_MIN_CODE_SIZE: u8 = 2;
+const MAX_GIF_CODE: u16 = 4095;
+
+/// Something that turns emulated frames into a video file.
+pub trait VideoEncoder {
+    /// Adds one frame, shown for one tick of `TIMER_FREQUENCY`.
+    fn push_frame(&mut self, gfx: &Framebuffer, palette: &[[u8; 3]; 4]) -> io::Result<()>;
+
+    /// Completes the file. No frames may be pushed afterwards.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Size of the video for a recording whose first frame is `gfx`. Later
+/// frames in another resolution are stretched to fit.
+fn video_size(gfx: &Framebuffer, scale: usize) -> (usize, usize) {
+    let scale = scale.max(1);
+    (gfx.width() * scale, gfx.height() * scale)
+}
+
+/// The pixel values of `gfx` stretched to `width` by `height`.
+fn scaled_pixels(gfx: &Framebuffer, width: usize, height: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = y * gfx.height() / height;
+        pixels.extend((0..width).map(|x| gfx.get(x * gfx.width() / width, row)));
+    }
+    pixels
+}
+
+struct GifFrame {
+    pixels: Vec<u8>,
+    palette: [[u8; 3]; 4],
+}
+
+/// Animated GIF writer that merges repeated frames into longer delays and
+/// only stores the rectangle that changed since the previous image.
+pub struct GifEncoder<W: Write> {
+    out: W,
+    scale: usize,
+    width: usize,
+    height: usize,
+    palette: [[u8; 3]; 4],
+    /// Image currently on screen in the GIF, empty before the first one.
+    shown: Vec<u8>,
+    shown_palette: [[u8; 3]; 4],
+    /// Latest frame, written once its duration is known.
+    pending: Option<GifFrame>,
+    ticks: u64,
+    /// Play time covered by the images written so far.
+    written_centis: u64,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// Each screen pixel becomes `scale` by `scale` pixels in the GIF.
+    pub fn new(out: W, scale: usize) -> Self {
+        Self {
+            out,
+            scale,
+            width: 0,
+            height: 0,
+            palette: [[0; 3]; 4],
+            shown: Vec::new(),
+            shown_palette: [[0; 3]; 4],
+            pending: None,
+            ticks: 0,
+            written_centis: 0,
+        }
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.out.write_all(b"GIF89a")?;
+        self.out.write_all(&(self.width as u16).to_le_bytes())?;
+        self.out.write_all(&(self.height as u16).to_le_bytes())?;
+        // Global colour table of 4 entries, background colour 0.
+        self.out.write_all(&[0x91, 0, 0])?;
+        self.out.write_all(self.palette.as_flattened())?;
+        // Loop forever.
+        self.out
+            .write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")
+    }
+
+    /// Writes the pending frame if it lasted long enough by `ticks`, or
+    /// unconditionally when `last` is set.
+    fn flush_pending(&mut self, last: bool) -> io::Result<()> {
+        let end = (self.ticks * 100 + TIMER_FREQUENCY as u64 / 2) / TIMER_FREQUENCY as u64;
+        let delay = end.saturating_sub(self.written_centis);
+        if delay < MIN_GIF_DELAY && !last {
+            return Ok(());
+        }
+
+        if let Some(frame) = self.pending.take() {
+            let delay = delay.max(MIN_GIF_DELAY);
+            self.write_image(&frame.pixels, &frame.palette, delay)?;
+            self.written_centis += delay;
+            self.shown = frame.pixels;
+            self.shown_palette = frame.palette;
+        }
+        Ok(())
+    }
+
+    fn write_image(&mut self, pixels: &[u8], palette: &[[u8; 3]; 4], delay: u64) -> io::Result<()> {
+        // A local colour table only recolours its own rectangle.
+        let (left, top, width, height) = if *palette == self.shown_palette {
+            changed_rect(&self.shown, pixels, self.width)
+        } else {
+            (0, 0, self.width, self.height)
+        };
+        let delay = delay.min(u16::MAX as u64) as u16;
+
+        // Graphic control extension: keep the previous image underneath.
+        self.out.write_all(&[0x21, 0xF9, 4, 0x04])?;
+        self.out.write_all(&delay.to_le_bytes())?;
+        self.out.write_all(&[0, 0])?;
+
+        self.out.write_all(&[0x2C])?;
+        for value in [left, top, width, height] {
+            self.out.write_all(&(value as u16).to_le_bytes())?;
+        }
+        if *palette == self.palette {
+            self.out.write_all(&[0])?;
+        } else {
+            self.out.write_all(&[0x81])?;
+            self.out.write_all(palette.as_flattened())?;
+        }
+
+        let cropped: Vec<u8> = (top..top + height)
+            .flat_map(|y| &pixels[y * self.width + left..y * self.width + left + width])
+            .copied()
+            .collect();
+        self.out.write_all(&[GIF_MIN_CODE_SIZE])?;
+        for block in lzw_encode(&cropped, GIF_MIN_CODE_SIZE).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0])
+    }
+}
+
+impl<W: Write> VideoEncoder for GifEncoder<W> {
+    fn push_frame(&mut self, gfx: &Framebuffer, palette: &[[u8; 3]; 4]) -> io::Result<()> {
+        if self.width == 0 {
+            (self.width, self.height) = video_size(gfx, self.scale);
+            self.palette = *palette;
+            self.write_header()?;
+        }
+
+        let pixels = scaled_pixels(gfx, self.width, self.height);
+        let repeated = self
+            .pending
+            .as_ref()
+            .is_some_and(|frame| frame.pixels == pixels && frame.palette == *palette);
+        if !repeated {
+            // A pending frame too short to show is replaced, and the next
+            // image inherits its time.
+            self.flush_pending(false)?;
+            self.pending = Some(GifFrame {
+                pixels,
+                palette: *palette,
+            });
+        }
+        self.ticks += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.width == 0 {
+            return Ok(());
+        }
+        self.flush_pending(true)?;
+        self.out.write_all(&[0x3B])?;
+        self.out.flush()
+    }
+}
+
+/// Bounding box `(left, top, width, height)` of the pixels that differ
+/// between two images. At least one pixel, so a frame can carry a delay.
+fn changed_rect(before: &[u8], after: &[u8], width: usize) -> (usize, usize, usize, usize) {
+    if before.len() != after.len() {
+        return (0, 0, width, after.len() / width);
+    }
+
+    let mut rect: Option<(usize, usize, usize, usize)> = None;
+    for (index, _) in before
+        .iter()
+        .zip(after)
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+    {
+        let (x, y) = (index % width, index / width);
+        rect = Some(match rect {
+            None => (x, y, x, y),
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        });
+    }
+
+    rect.map_or((0, 0, 1, 1), |(x0, y0, x1, y1)| {
+        (x0, y0, x1 - x0 + 1, y1 - y0 + 1)
+    })
+}
+
+/// Compresses `pixels` with GIF's variable-width LZW, packed LSB first.
+fn lzw_encode(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let alphabet = clear as usize;
+
+    let mut out = BitWriter::default();
+    // Child code for each (code, next pixel), 0 meaning none.
+    let mut table = vec![0u16; (MAX_GIF_CODE as usize + 1) * alphabet];
+    let mut code_size = min_code_size + 1;
+    let mut next = end + 1;
+
+    out.put(clear, code_size);
+    let mut pixels = pixels.iter().map(|&pixel| pixel as usize);
+    let Some(mut prefix) = pixels.next().map(|pixel| pixel as u16) else {
+        out.put(end, code_size);
+        return out.finish();
+    };
+
+    for pixel in pixels {
+        let slot = prefix as usize * alphabet + pixel;
+        if table[slot] != 0 {
+            prefix = table[slot];
+            continue;
+        }
+
+        out.put(prefix, code_size);
+        if next <= MAX_GIF_CODE {
+            table[slot] = next;
+            if next == 1 << code_size {
+                code_size += 1;
+            }
+            next += 1;
+        } else {
+            out.put(clear, code_size);
+            table.fill(0);
+            code_size = min_code_size + 1;
+            next = end + 1;
+        }
+        prefix = pixel as u16;
+    }
+
+    out.put(prefix, code_size);
+    // The decoder adds its last entry on reading `prefix`, and may widen
+    // the codes before the end code.
+    if next == 1 << code_size && next <= MAX_GIF_CODE {
+        code_size += 1;
+    }
+    out.put(end, code_size);
+    out.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn put(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Uncompressed YUV 4:4:4 video in the YUV4MPEG2 format, for feeding into
+/// a real encoder such as ffmpeg.
+pub struct Y4mEncoder<W: Write> {
+    out: W,
+    scale: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> Y4mEncoder<W> {
+    /// Each screen pixel becomes `scale` by `scale` pixels in the video.
+    pub fn new(out: W, scale: usize) -> Self {
+        Self {
+            out,
+            scale,
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+impl<W: Write> VideoEncoder for Y4mEncoder<W> {
+    fn push_frame(&mut self, gfx: &Framebuffer, palette: &[[u8; 3]; 4]) -> io::Result<()> {
+        if self.width == 0 {
+            (self.width, self.height) = video_size(gfx, self.scale);
+            writeln!(
+                self.out,
+                "YUV4MPEG2 W{} H{} F{TIMER_FREQUENCY}:1 Ip A1:1 C444",
+                self.width, self.height
+            )?;
+        }
+
+        let colours = palette.map(ycbcr);
+        let pixels = scaled_pixels(gfx, self.width, self.height);
+        let mut frame = Vec::with_capacity(6 + pixels.len() * 3);
+        frame.extend_from_slice(b"FRAME\n");
+        for plane in 0..3 {
+            let values = colours.map(|colour| colour[plane]);
+            frame.extend(pixels.iter().map(|&pixel| values[pixel as usize]));
+        }
+        self.out.write_all(&frame)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// BT.601 studio-range Y'CbCr of an RGB colour.
+fn ycbcr([r, g, b]: [u8; 3]) -> [u8; 3] {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let cb = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let cr = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, cb as u8, cr as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::framebuffer::{CHIP8_HEIGHT, CHIP8_WIDTH},
+        palette::Palette,
+    };
+
+    /// Reference GIF LZW decoder.
+    fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut code_size = min_code_size + 1;
+        let mut out = Vec::new();
+        let mut previous: Option<usize> = None;
+        let (mut buffer, mut bits, mut bytes) = (0u32, 0u8, data.iter());
+
+        loop {
+            while bits < code_size {
+                buffer |= (*bytes.next().expect("end code") as u32) << bits;
+                bits += 8;
+            }
+            let code = (buffer & ((1 << code_size) - 1)) as usize;
+            buffer >>= code_size;
+            bits -= code_size;
+
+            if code == clear {
+                table = (0..clear).map(|value| vec![value as u8]).collect();
+                table.extend([Vec::new(), Vec::new()]);
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return out;
+            }
+
+            let entry = match (table.get(code), previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => {
+                    let mut entry = table[previous].clone();
+                    entry.push(table[previous][0]);
+                    entry
+                }
+                (None, None) => panic!("code {code} before any entry"),
+            };
+            if let Some(previous) = previous {
+                if table.len() < 4096 {
+                    let mut added = table[previous].clone();
+                    added.push(entry[0]);
+                    table.push(added);
+                    if table.len() == 1 << code_size && code_size < 12 {
+                        code_size += 1;
+                    }
+                }
+            }
+            out.extend_from_slice(&entry);
+            previous = Some(code);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trips_past_a_full_table() {
+        let mut state = 1u32;
+        let pixels: Vec<u8> = (0..40_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 29) as u8 & 3
+            })
+            .collect();
+        assert_eq!(lzw_decode(&lzw_encode(&pixels, 2), 2), pixels);
+        assert_eq!(lzw_decode(&lzw_encode(&[3; 5000], 2), 2), vec![3; 5000]);
+    }
+
+    #[test]
+    fn lzw_round_trips_at_every_code_size_boundary() {
+        for len in 1..300 {
+            let pixels: Vec<u8> = (0..len).map(|i| (i * 7 / 3 % 5 % 4) as u8).collect();
+            assert_eq!(lzw_decode(&lzw_encode(&pixels, 2), 2), pixels, "{len}");
+        }
+    }
+
+    #[test]
+    fn gif_merges_repeated_frames() {
+        let mut gfx = Framebuffer::new(CHIP8_WIDTH, CHIP8_HEIGHT);
+        let mut gif = GifEncoder::new(Vec::new(), 2);
+        for _ in 0..30 {
+            gif.push_frame(&gfx, &Palette::default().colours).unwrap();
+        }
+        gfx.toggle(5, 5, 1);
+        for _ in 0..30 {
+            gif.push_frame(&gfx, &Palette::default().colours).unwrap();
+        }
+        gif.finish().unwrap();
+
+        let data = gif.out;
+        assert!(data.starts_with(b"GIF89a\x80\x00\x40\x00"));
+        assert_eq!(data.last(), Some(&0x3B));
+        // Two images of half a second each.
+        let delays: Vec<u16> = data
+            .windows(4)
+            .enumerate()
+            .filter(|(_, window)| window[..3] == [0x21, 0xF9, 4])
+            .map(|(index, _)| u16::from_le_bytes([data[index + 4], data[index + 5]]))
+            .collect();
+        assert_eq!(delays, [50, 50]);
+    }
+
+    #[test]
+    fn changed_rect_covers_the_differences() {
+        let before = vec![0; 16];
+        let mut after = before.clone();
+        after[5] = 1;
+        after[10] = 2;
+        assert_eq!(changed_rect(&before, &after, 4), (1, 1, 2, 2));
+        assert_eq!(changed_rect(&after, &after, 4), (0, 0, 1, 1));
+        assert_eq!(changed_rect(&[], &after, 4), (0, 0, 4, 4));
+    }
+
+    #[test]
+    fn y4m_writes_planar_frames() {
+        let gfx = Framebuffer::new(CHIP8_WIDTH, CHIP8_HEIGHT);
+        let mut y4m = Y4mEncoder::new(Vec::new(), 1);
+        y4m.push_frame(&gfx, &Palette::default().colours).unwrap();
+        y4m.push_frame(&gfx, &Palette::default().colours).unwrap();
+
+        let header = b"YUV4MPEG2 W64 H32 F60:1 Ip A1:1 C444\n";
+        assert!(y4m.out.starts_with(header));
+        assert_eq!(y4m.out.len(), header.len() + 2 * (6 + 64 * 32 * 3));
+    }
+}
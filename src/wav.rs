@@ -0,0 +1,157 @@
+//! Sound timer capture to 16-bit mono WAV. Samples are synthesised from
+//! emulated frames rather than taken from the host audio device, so the same
+//! run always produces the same file.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::models::{
+    audio::{AudioPattern, Synth},
+    chip8::TIMER_FREQUENCY,
+};
+
+pub const WAV_SAMPLE_RATE: u32 = 44_100;
+/// Sample value of a high output level; low levels are its negation.
+const AMPLITUDE: i16 = 8192;
+const HEADER_LEN: u32 = 44;
+
+/// Writes one frame of samples per emulated 1/60 s tick.
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    synth: Synth,
+    sample_rate: u32,
+    frames: u64,
+    samples: u64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Starts the file with a header that `finish` completes.
+    pub fn new(mut out: W, sample_rate: u32, beep_frequency: f32) -> io::Result<Self> {
+        out.write_all(&header(sample_rate, 0))?;
+        Ok(Self {
+            out,
+            synth: Synth::new(sample_rate, beep_frequency),
+            sample_rate,
+            frames: 0,
+            samples: 0,
+        })
+    }
+
+    /// Adds one tick of `TIMER_FREQUENCY`, playing the beep or `pattern`
+    /// while `sound_active` and silence otherwise.
+    pub fn push_frame(
+        &mut self,
+        sound_active: bool,
+        pattern: Option<AudioPattern>,
+    ) -> io::Result<()> {
+        self.frames += 1;
+        let end = self.frames * self.sample_rate as u64 / TIMER_FREQUENCY as u64;
+        let count = (end - self.samples) as usize;
+        self.samples = end;
+
+        let mut data = Vec::with_capacity(count * 2);
+        for _ in 0..count {
+            let sample = if !sound_active {
+                0
+            } else if self.synth.next_sample(pattern.as_ref()) {
+                AMPLITUDE
+            } else {
+                -AMPLITUDE
+            };
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        self.out.write_all(&data)
+    }
+
+    /// Fills in the data size in the header. No frames may be pushed afterwards.
+    pub fn finish(&mut self) -> io::Result<()> {
+        let data_len = u32::try_from(self.samples * 2)
+            .ok()
+            .filter(|len| *len <= u32::MAX - HEADER_LEN)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "WAV file over 4 GiB"))?;
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header(self.sample_rate, data_len))?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()
+    }
+}
+
+/// RIFF header for `data_len` bytes of 16-bit mono PCM.
+fn header(sample_rate: u32, data_len: u32) -> [u8; HEADER_LEN as usize] {
+    let mut header = [0; HEADER_LEN as usize];
+    let fields: [&[u8]; 13] = [
+        b"RIFF",
+        &(HEADER_LEN - 8 + data_len).to_le_bytes(),
+        b"WAVE",
+        b"fmt ",
+        &16u32.to_le_bytes(),
+        &1u16.to_le_bytes(),
+        &1u16.to_le_bytes(),
+        &sample_rate.to_le_bytes(),
+        &(sample_rate * 2).to_le_bytes(),
+        &2u16.to_le_bytes(),
+        &16u16.to_le_bytes(),
+        b"data",
+        &data_len.to_le_bytes(),
+    ];
+    let mut offset = 0;
+    for field in fields {
+        header[offset..offset + field.len()].copy_from_slice(field);
+        offset += field.len();
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn samples(wav: &[u8]) -> Vec<i16> {
+        wav[HEADER_LEN as usize..]
+            .chunks(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn writes_one_second_per_sixty_frames() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 1000, 125.0).unwrap();
+        for frame in 0..60 {
+            wav.push_frame(frame < 30, None).unwrap();
+        }
+        wav.finish().unwrap();
+        let data = wav.out.into_inner();
+
+        assert_eq!(&data[..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(data[4..8].try_into().unwrap()),
+            36 + 2000
+        );
+        assert_eq!(u32::from_le_bytes(data[24..28].try_into().unwrap()), 1000);
+        assert_eq!(u32::from_le_bytes(data[40..44].try_into().unwrap()), 2000);
+
+        let samples = samples(&data);
+        assert_eq!(samples.len(), 1000);
+        assert_eq!(&samples[..5], &[AMPLITUDE; 5]);
+        assert_eq!(&samples[5..8], &[-AMPLITUDE; 3]);
+        assert_eq!(samples[8], AMPLITUDE);
+        assert!(samples[500..].iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn plays_the_pattern_at_its_rate() {
+        let mut buffer = [0; 16];
+        buffer[0] = 0b1100_0000;
+        let pattern = AudioPattern { buffer, pitch: 64 };
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 8000, 440.0).unwrap();
+        wav.push_frame(true, Some(pattern)).unwrap();
+        wav.finish().unwrap();
+
+        // 4000 bits/s at 8000 samples/s holds each bit for two samples.
+        let samples = samples(&wav.out.into_inner());
+        assert_eq!(samples.len(), 133);
+        assert_eq!(&samples[..4], &[AMPLITUDE; 4]);
+        assert!(samples[4..133].iter().all(|&sample| sample == -AMPLITUDE));
+    }
+}
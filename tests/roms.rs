@@ -0,0 +1,105 @@
+//! Runs the bundled test ROMs headlessly and compares the final screen with
+//! the golden images in `tests/golden`. Set `UPDATE_GOLDEN=1` to rewrite them.
+//! Every bundled ROM must also survive disassembly to Octo and reassembly.
+
+use std::path::PathBuf;
+
+use chip_8::{
+    asm::assemble,
+    disasm::disassemble_octo,
+    input::keyboard::Keyboard,
+    models::{chip8::Chip8, framebuffer::Framebuffer, quirks::Quirks},
+};
+
+const FRAMES: u32 = 300;
+const CYCLES_PER_FRAME: u32 = 10;
+
+fn run(rom: &str, quirks: Quirks) -> Chip8 {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("roms")
+        .join(rom);
+    let program = std::fs::read(&path).unwrap();
+    let mut chip = Chip8::new(program, quirks).unwrap().with_seed(0);
+
+    let keyboard = Keyboard::new();
+    for _ in 0..FRAMES {
+        chip.run_frame(&keyboard, CYCLES_PER_FRAME).unwrap();
+    }
+    chip
+}
+
+/// One line per row: `.` for an unlit pixel, otherwise its plane bits.
+fn render(gfx: &Framebuffer) -> String {
+    gfx.rows()
+        .map(|row| {
+            let mut line: String = row
+                .iter()
+                .map(|&pixel| match pixel {
+                    0 => '.',
+                    1 => '#',
+                    _ => char::from_digit(pixel as u32, 4).unwrap(),
+                })
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+fn assert_golden(name: &str, gfx: &Framebuffer) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.txt"));
+    let actual = render(gfx);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Cannot read {}: {err}", path.display()));
+    assert!(
+        actual == expected,
+        "{name} does not match its golden image\nexpected:\n{expected}\nactual:\n{actual}"
+    );
+}
+
+#[test]
+fn test_opcode() {
+    let chip = run("test_opcode.ch8", Quirks::COSMAC_VIP);
+    assert_golden("test_opcode", chip.framebuffer());
+}
+
+#[test]
+fn chip8_test_rom() {
+    let chip = run("chip8-test-rom.ch8", Quirks::COSMAC_VIP);
+    assert_golden("chip8-test-rom", chip.framebuffer());
+}
+
+#[test]
+fn ch8_test() {
+    // SCTEST expects SUPER-CHIP shifts and loads, but a VIP-style BNNN.
+    let mut quirks = Quirks::SUPER_CHIP;
+    quirks.jumping = false;
+    let chip = run("ch8_test.ch8", quirks);
+    assert_golden("ch8_test", chip.framebuffer());
+}
+
+#[test]
+fn ibm_logo() {
+    let chip = run("IBM Logo.ch8", Quirks::COSMAC_VIP);
+    assert_golden("ibm_logo", chip.framebuffer());
+}
+
+#[test]
+fn octo_disassembly_round_trips() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = std::fs::read(&path).unwrap();
+        let source = disassemble_octo(&rom);
+        let assembled = assemble(&source).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        assert!(assembled == rom, "{} does not round-trip", path.display());
+    }
+}